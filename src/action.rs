@@ -1,11 +1,24 @@
+use std::{
+    collections::{HashMap, HashSet},
+    iter, slice,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
 use casbin::{error::AdapterError, Error as CasbinError, Filter, Result};
+use futures_util::TryStreamExt;
 use sea_orm::{
+    sea_query::{Asterisk, Expr, Func, OnConflict, Query},
     ActiveModelTrait,
     ActiveValue::{NotSet, Set},
-    ColumnTrait, Condition, ConnectionTrait, EntityTrait, QueryFilter,
+    ColumnTrait, Condition, ConnectionTrait, DbErr, EntityTrait, QueryFilter, QueryOrder, SqlErr,
+    StreamTrait,
 };
+use sha2::{Digest, Sha256};
 
-use crate::entity::{self, Column, Entity};
+use crate::{
+    entity::{self, revision, Column, Entity},
+    telemetry, Error,
+};
 
 #[derive(Debug, Default)]
 pub(crate) struct Rule<'a> {
@@ -52,6 +65,12 @@ pub(crate) struct RuleWithType<'a> {
     pub(crate) v3: &'a str,
     pub(crate) v4: &'a str,
     pub(crate) v5: &'a str,
+    /// Number of values when trailing empty values are preserved.
+    pub(crate) arity: Option<i32>,
+    /// Whether the unused positions are stored as `NULL`.
+    pub(crate) nullable: bool,
+    /// Whether the rule is unique by its `rule_hash`.
+    pub(crate) hashed: bool,
 }
 
 impl<'a> RuleWithType<'a> {
@@ -64,45 +83,310 @@ impl<'a> RuleWithType<'a> {
             v3: rule.v3,
             v4: rule.v4,
             v5: rule.v5,
+            arity: None,
+            nullable: false,
+            hashed: false,
+        }
+    }
+
+    // in nullable mode, the empty values after the last non-empty one are unused positions
+    pub(crate) fn columns(&self) -> [Option<&'a str>; 6] {
+        let values = [self.v0, self.v1, self.v2, self.v3, self.v4, self.v5];
+        let used = if self.nullable {
+            values
+                .iter()
+                .rposition(|x| !x.is_empty())
+                .map_or(0, |x| x + 1)
+        } else {
+            values.len()
+        };
+
+        std::array::from_fn(|index| (index < used).then_some(values[index]))
+    }
+
+    // each column is prefixed with its length, so that values can't run into the next one
+    pub(crate) fn hash(&self) -> String {
+        let mut hasher = Sha256::new();
+
+        for value in iter::once(Some(self.ptype)).chain(self.columns()) {
+            match value {
+                Some(value) => {
+                    hasher.update([1]);
+                    hasher.update((value.len() as u64).to_le_bytes());
+                    hasher.update(value);
+                }
+                None => hasher.update([0]),
+            }
+        }
+
+        hasher
+            .finalize()
+            .iter()
+            .map(|x| format!("{x:02x}"))
+            .collect()
+    }
+
+    pub(crate) fn to_active_model(&self, expires_at: Option<i64>) -> entity::ActiveModel {
+        let [v0, v1, v2, v3, v4, v5] = self.columns().map(|x| Set(x.map(str::to_owned)));
+
+        entity::ActiveModel {
+            id: NotSet,
+            ptype: Set(self.ptype.to_owned()),
+            v0,
+            v1,
+            v2,
+            v3,
+            v4,
+            v5,
+            expires_at: Set(expires_at),
+            deleted_at: Set(None),
+            arity: Set(self.arity),
+            rule_hash: Set(self.hashed.then(|| self.hash())),
         }
     }
 }
 
+pub(crate) const VALUE_COLUMNS: [Column; 6] = [
+    Column::V0,
+    Column::V1,
+    Column::V2,
+    Column::V3,
+    Column::V4,
+    Column::V5,
+];
+
+// keeps each statement well below the bind parameter limits of every backend
+pub(crate) const BATCH_SIZE: usize = 100;
+
+pub(crate) fn unix_timestamp(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |x| i64::try_from(x.as_secs()).unwrap_or(i64::MAX))
+}
+
+pub(crate) fn exact_match(rule: &RuleWithType<'_>) -> Condition {
+    if rule.hashed {
+        return Condition::all().add(Column::RuleHash.eq(rule.hash()));
+    }
+
+    VALUE_COLUMNS.into_iter().zip(rule.columns()).fold(
+        Condition::all().add(Column::Ptype.eq(rule.ptype)),
+        |conditions, (column, value)| match value {
+            Some(value) => conditions.add(column.eq(value)),
+            None => conditions.add(column.is_null()),
+        },
+    )
+}
+
+pub(crate) fn is_active(now: i64) -> Condition {
+    Condition::all().add(Column::DeletedAt.is_null()).add(
+        Condition::any()
+            .add(Column::ExpiresAt.is_null())
+            .add(Column::ExpiresAt.gt(now)),
+    )
+}
+
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(backend = ?conn.get_database_backend(), soft_delete = soft_delete, rows_affected = tracing::field::Empty)
+    )
+)]
+async fn delete_where<C: ConnectionTrait>(
+    conn: &C,
+    soft_delete: bool,
+    conditions: Condition,
+) -> Result<u64> {
+    let result = if soft_delete {
+        let now = unix_timestamp(SystemTime::now());
+
+        Entity::update_many()
+            .col_expr(Column::DeletedAt, Expr::value(now))
+            .filter(conditions)
+            .filter(Column::DeletedAt.is_null())
+            .exec(conn)
+            .await
+            .map(|x| x.rows_affected)
+    } else {
+        Entity::delete_many()
+            .filter(conditions)
+            .exec(conn)
+            .await
+            .map(|x| x.rows_affected)
+    };
+
+    let rows_affected = result.map_err(|err| CasbinError::from(AdapterError(Box::new(err))))?;
+    telemetry::record("rows_affected", rows_affected);
+
+    Ok(rows_affected)
+}
+
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(backend = ?conn.get_database_backend(), ptype = rule.ptype, rows_affected = tracing::field::Empty)
+    )
+)]
 pub(crate) async fn remove_policy<'conn, 'rule, C: ConnectionTrait>(
     conn: &'conn C,
+    soft_delete: bool,
     rule: RuleWithType<'rule>,
 ) -> Result<bool> {
-    Entity::delete_many()
-        .filter(Column::Ptype.eq(rule.ptype))
-        .filter(Column::V0.eq(rule.v0))
-        .filter(Column::V1.eq(rule.v1))
-        .filter(Column::V2.eq(rule.v2))
-        .filter(Column::V3.eq(rule.v3))
-        .filter(Column::V4.eq(rule.v4))
-        .filter(Column::V5.eq(rule.v5))
-        .exec(conn)
-        .await
-        .map(|count| count.rows_affected == 1)
-        .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))
+    let removed = delete_where(conn, soft_delete, exact_match(&rule)).await?;
+    telemetry::record("rows_affected", removed);
+
+    Ok(removed == 1)
 }
 
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(backend = ?conn.get_database_backend(), ptype = rules.first().map_or("", |x| x.ptype), rows_affected = tracing::field::Empty)
+    )
+)]
+/// Returns the number of rules removed.
 pub(crate) async fn remove_policies<'conn, 'rule, C: ConnectionTrait>(
     conn: &'conn C,
+    soft_delete: bool,
     rules: Vec<RuleWithType<'rule>>,
-) -> Result<bool> {
+) -> Result<u64> {
+    let mut count = 0;
+
     for rule in rules {
-        remove_policy(conn, rule).await?;
+        count += u64::from(remove_policy(conn, soft_delete, rule).await?);
     }
 
-    Ok(true)
+    telemetry::record("rows_affected", count);
+
+    Ok(count)
 }
 
-pub(crate) async fn remove_filtered_policy<'conn, 'rule, C: ConnectionTrait>(
-    conn: &'conn C,
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(backend = ?conn.get_database_backend(), ptype = ptype, field_index = index_of_match_start, rows_affected = tracing::field::Empty)
+    )
+)]
+pub(crate) async fn remove_filtered_policy<'rule, C: ConnectionTrait>(
+    conn: &C,
+    soft_delete: bool,
     ptype: &'rule str,
     index_of_match_start: usize,
     rule: Rule<'rule>,
+) -> Result<u64> {
+    let conditions = filtered_condition(ptype, index_of_match_start, rule);
+    let removed = delete_where(conn, soft_delete, conditions).await?;
+    telemetry::record("rows_affected", removed);
+
+    Ok(removed)
+}
+
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(backend = ?conn.get_database_backend(), ptype = rule.ptype)
+    )
+)]
+pub(crate) async fn has_policy<C: ConnectionTrait>(
+    conn: &C,
+    rule: RuleWithType<'_>,
 ) -> Result<bool> {
+    let conditions = Condition::all()
+        .add(exact_match(&rule))
+        .add(is_active(unix_timestamp(SystemTime::now())));
+
+    has_row(conn, conditions).await
+}
+
+async fn has_row<C: ConnectionTrait>(conn: &C, conditions: Condition) -> Result<bool> {
+    let select = Query::select()
+        .expr(Expr::val(1))
+        .from(Entity)
+        .cond_where(conditions)
+        .limit(1)
+        .to_owned();
+
+    let builder = conn.get_database_backend();
+
+    conn.query_one(builder.build(&select))
+        .await
+        .map(|row| row.is_some())
+        .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))
+}
+
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(backend = ?conn.get_database_backend(), ptype = ptype, field_index = index_of_match_start, rules = tracing::field::Empty)
+    )
+)]
+pub(crate) async fn count_filtered_policy<'rule, C: ConnectionTrait>(
+    conn: &C,
+    ptype: &'rule str,
+    index_of_match_start: usize,
+    rule: Rule<'rule>,
+) -> Result<u64> {
+    let select = Query::select()
+        .expr(Func::count(Expr::col(Asterisk)))
+        .from(Entity)
+        .cond_where(filtered_condition(ptype, index_of_match_start, rule))
+        .cond_where(is_active(unix_timestamp(SystemTime::now())))
+        .to_owned();
+
+    let builder = conn.get_database_backend();
+
+    let count = conn
+        .query_one(builder.build(&select))
+        .await
+        .and_then(|row| match row {
+            Some(row) => row.try_get_by_index::<i64>(0),
+            None => Ok(0),
+        })
+        .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))?;
+    telemetry::record("rules", count.max(0) as u64);
+
+    Ok(count.max(0) as u64)
+}
+
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(backend = ?conn.get_database_backend(), ptype = ptype, field_index = index_of_match_start, rules = tracing::field::Empty)
+    )
+)]
+pub(crate) async fn load_filtered_rules<'rule, C: ConnectionTrait>(
+    conn: &C,
+    ptype: &'rule str,
+    index_of_match_start: usize,
+    rule: Rule<'rule>,
+) -> Result<Vec<entity::Model>> {
+    let rules = entity::Entity::find()
+        .filter(filtered_condition(ptype, index_of_match_start, rule))
+        .filter(is_active(unix_timestamp(SystemTime::now())))
+        .order_by_asc(Column::Id)
+        .all(conn)
+        .await
+        .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))?;
+    telemetry::record("rules", rules.len() as u64);
+
+    Ok(rules)
+}
+
+// empty values match anything, like casbin's `remove_filtered_policy`
+fn filtered_condition(ptype: &str, index_of_match_start: usize, rule: Rule<'_>) -> Condition {
     let mut conditions = Condition::all().add(Column::Ptype.eq(ptype));
 
     if index_of_match_start == 0 {
@@ -139,34 +423,128 @@ pub(crate) async fn remove_filtered_policy<'conn, 'rule, C: ConnectionTrait>(
         conditions = conditions.add_option((!rule.v0.is_empty()).then(|| Column::V5.eq(rule.v0)));
     }
 
-    Entity::delete_many()
-        .filter(conditions)
-        .exec(conn)
-        .await
-        .map(|count| count.rows_affected >= 1)
-        .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))
+    conditions
 }
 
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(backend = ?conn.get_database_backend(), rules = tracing::field::Empty)
+    )
+)]
 pub(crate) async fn load_policy<C: ConnectionTrait>(conn: &C) -> Result<Vec<entity::Model>> {
-    entity::Entity::find()
+    let rules = entity::Entity::find()
+        .filter(is_active(unix_timestamp(SystemTime::now())))
+        .order_by_asc(Column::Id)
         .all(conn)
         .await
-        .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))
+        .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))?;
+    telemetry::record("rules", rules.len() as u64);
+
+    Ok(rules)
+}
+
+/// Streams the rules that `load_policy` would return, without holding all of them in memory.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(backend = ?conn.get_database_backend(), rules = tracing::field::Empty)
+    )
+)]
+pub(crate) async fn for_each_policy<C, F>(conn: &C, mut f: F) -> Result<()>
+where
+    C: ConnectionTrait + StreamTrait + Send,
+    F: FnMut(entity::Model) -> Result<()>,
+{
+    let mut count = 0;
+    let mut stream = entity::Entity::find()
+        .filter(is_active(unix_timestamp(SystemTime::now())))
+        .order_by_asc(Column::Id)
+        .stream(conn)
+        .await
+        .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))?;
+
+    while let Some(rule) = stream
+        .try_next()
+        .await
+        .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))?
+    {
+        f(rule)?;
+        count += 1;
+    }
+
+    telemetry::record("rules", count);
+
+    Ok(())
+}
+
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(backend = ?conn.get_database_backend(), rules = tracing::field::Empty)
+    )
+)]
+pub(crate) async fn load_undeleted_policy<C: ConnectionTrait>(
+    conn: &C,
+) -> Result<Vec<entity::Model>> {
+    let rules = entity::Entity::find()
+        .filter(Column::DeletedAt.is_null())
+        .order_by_asc(Column::Id)
+        .all(conn)
+        .await
+        .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))?;
+    telemetry::record("rules", rules.len() as u64);
+
+    Ok(rules)
 }
 
+// a ptype belongs to the section named by its first character, unless it is mapped explicitly
+fn in_section(sections: &HashMap<String, String>, sec: &str) -> Condition {
+    let mapped = sections
+        .iter()
+        .filter(|(_, x)| *x == sec)
+        .map(|(ptype, _)| ptype.as_str())
+        .collect::<Vec<_>>();
+
+    let by_first_char = (sec.chars().count() == 1).then(|| {
+        Condition::all()
+            .add(Column::Ptype.starts_with(sec))
+            .add_option((!sections.is_empty()).then(|| Column::Ptype.is_not_in(sections.keys())))
+    });
+
+    Condition::any()
+        .add_option(by_first_char)
+        .add_option((!mapped.is_empty()).then(|| Column::Ptype.is_in(mapped)))
+}
+
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(backend = ?conn.get_database_backend(), rules = tracing::field::Empty)
+    )
+)]
 pub(crate) async fn load_filtered_policy<'conn, 'filter, C: ConnectionTrait>(
     conn: &'conn C,
+    sections: &HashMap<String, String>,
     filter: Filter<'filter>,
 ) -> Result<Vec<entity::Model>> {
     let g_filter = Rule::from_str(&filter.g);
     let p_filter = Rule::from_str(&filter.p);
 
-    entity::Entity::find()
+    let rules = entity::Entity::find()
         .filter(
             Condition::any()
                 .add(
                     Condition::all()
-                        .add(Column::Ptype.starts_with("g"))
+                        .add(in_section(sections, "g"))
                         .add_option((!g_filter.v0.is_empty()).then(|| Column::V0.eq(g_filter.v0)))
                         .add_option((!g_filter.v1.is_empty()).then(|| Column::V1.eq(g_filter.v1)))
                         .add_option((!g_filter.v2.is_empty()).then(|| Column::V2.eq(g_filter.v2)))
@@ -176,7 +554,7 @@ pub(crate) async fn load_filtered_policy<'conn, 'filter, C: ConnectionTrait>(
                 )
                 .add(
                     Condition::all()
-                        .add(Column::Ptype.starts_with("p"))
+                        .add(in_section(sections, "p"))
                         .add_option((!p_filter.v0.is_empty()).then(|| Column::V0.eq(p_filter.v0)))
                         .add_option((!p_filter.v1.is_empty()).then(|| Column::V1.eq(p_filter.v1)))
                         .add_option((!p_filter.v2.is_empty()).then(|| Column::V2.eq(p_filter.v2)))
@@ -185,59 +563,552 @@ pub(crate) async fn load_filtered_policy<'conn, 'filter, C: ConnectionTrait>(
                         .add_option((!p_filter.v5.is_empty()).then(|| Column::V5.eq(p_filter.v5))),
                 ),
         )
+        .filter(is_active(unix_timestamp(SystemTime::now())))
+        .order_by_asc(Column::Id)
         .all(conn)
         .await
-        .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))
+        .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))?;
+    telemetry::record("rules", rules.len() as u64);
+
+    Ok(rules)
 }
 
+// the name of the row of `casbin_revision` counting the saves of `casbin_rule`
+const POLICY_REVISION: &str = "policy";
+
+/// `0` until the first save.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(backend = ?conn.get_database_backend())
+    )
+)]
+pub(crate) async fn load_revision<C: ConnectionTrait>(conn: &C) -> Result<i64> {
+    let row = revision::Entity::find_by_id(POLICY_REVISION)
+        .one(conn)
+        .await
+        .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))?;
+
+    Ok(row.map_or(0, |x| x.revision))
+}
+
+/// Replaces the rules if the revision is still `revision`, and returns the new revision and the
+/// numbers of rules removed and inserted. Without a `revision`, e.g. before the first load, the rules are
+/// replaced anyway. `txn` must be a transaction, so that the rules and revision change together.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(backend = ?txn.get_database_backend(), revision = ?revision, rows_affected = tracing::field::Empty)
+    )
+)]
 pub(crate) async fn save_policies<'conn, 'rule, C: ConnectionTrait>(
-    conn: &'conn C,
+    txn: &'conn C,
+    soft_delete: bool,
     rules: Vec<RuleWithType<'rule>>,
-) -> Result<()> {
-    clear_policy(conn).await?;
-    add_policies(conn, rules).await?;
-    Ok(())
+    revision: Option<i64>,
+) -> Result<(i64, u64, u64)> {
+    // the row stays locked until the commit, concurrent saves wait and then find another revision
+    let next = bump_revision(txn, revision).await?;
+    let removed = clear_policy(txn, soft_delete).await?;
+
+    // the table is empty after a hard delete, so there is nothing to revive
+    let inserted = if soft_delete {
+        add_policies(txn, rules).await?
+    } else {
+        for chunk in rules.chunks(BATCH_SIZE) {
+            insert_policies(txn, &chunk.iter().collect::<Vec<_>>()).await?;
+        }
+
+        rules.len() as u64
+    };
+    telemetry::record("rows_affected", inserted);
+
+    Ok((next, removed, inserted))
 }
 
+/// Increments the revision if it's still `revision`, or unconditionally without one, so that the
+/// saves of the writers that loaded the rules before fail. Returns the new revision.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(backend = ?conn.get_database_backend(), revision = ?revision, rows_affected = tracing::field::Empty)
+    )
+)]
+pub(crate) async fn bump_revision<C: ConnectionTrait>(
+    conn: &C,
+    revision: Option<i64>,
+) -> Result<i64> {
+    let Some(expected) = revision else {
+        // the first change creates the row, a concurrent one increments it instead
+        let row = revision::ActiveModel {
+            name: Set(POLICY_REVISION.to_owned()),
+            revision: Set(1),
+        };
+        let on_conflict = OnConflict::column(revision::Column::Name)
+            .value(
+                revision::Column::Revision,
+                Expr::col((revision::Entity, revision::Column::Revision)).add(1),
+            )
+            .to_owned();
+
+        revision::Entity::insert(row)
+            .on_conflict(on_conflict)
+            .exec_without_returning(conn)
+            .await
+            .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))?;
+        // MySQL counts an updated row twice
+        telemetry::record("rows_affected", 1);
+
+        return load_revision(conn).await;
+    };
+
+    let result = revision::Entity::update_many()
+        .col_expr(
+            revision::Column::Revision,
+            Expr::col(revision::Column::Revision).add(1),
+        )
+        .filter(revision::Column::Name.eq(POLICY_REVISION))
+        .filter(revision::Column::Revision.eq(expected))
+        .exec(conn)
+        .await
+        .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))?;
+
+    let found = load_revision(conn).await?;
+    telemetry::record("rows_affected", result.rows_affected);
+
+    if result.rows_affected == 1 {
+        return Ok(found);
+    }
+
+    // a concurrent first save inserts the same row
+    let conflict =
+        || CasbinError::from(AdapterError(Box::new(Error::Conflict { expected, found })));
+
+    if found != 0 || expected != 0 {
+        return Err(conflict());
+    }
+
+    let row = revision::ActiveModel {
+        name: Set(POLICY_REVISION.to_owned()),
+        revision: Set(1),
+    };
+
+    match revision::Entity::insert(row).exec(conn).await {
+        Ok(_) => {
+            telemetry::record("rows_affected", 1);
+            Ok(1)
+        }
+        Err(err) if matches!(err.sql_err(), Some(SqlErr::UniqueConstraintViolation(_))) => {
+            Err(conflict())
+        }
+        Err(err) => Err(CasbinError::from(AdapterError(Box::new(err)))),
+    }
+}
+
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(backend = ?conn.get_database_backend(), ptype = rule.ptype)
+    )
+)]
 pub(crate) async fn add_policy<'conn, 'rule, C: ConnectionTrait>(
     conn: &'conn C,
     rule: RuleWithType<'rule>,
 ) -> Result<bool> {
-    let model = entity::ActiveModel {
-        id: NotSet,
-        ptype: Set(rule.ptype.to_string()),
-        v0: Set(rule.v0.to_string()),
-        v1: Set(rule.v1.to_string()),
-        v2: Set(rule.v2.to_string()),
-        v3: Set(rule.v3.to_string()),
-        v4: Set(rule.v4.to_string()),
-        v5: Set(rule.v5.to_string()),
-    };
+    add_policy_with_expiry(conn, rule, None).await
+}
 
-    model
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(backend = ?conn.get_database_backend(), ptype = rule.ptype, rows_affected = tracing::field::Empty)
+    )
+)]
+pub(crate) async fn add_policy_with_expiry<'conn, 'rule, C: ConnectionTrait>(
+    conn: &'conn C,
+    rule: RuleWithType<'rule>,
+    expires_at: Option<i64>,
+) -> Result<bool> {
+    let stored = find_stored(conn, slice::from_ref(&rule)).await?;
+
+    match stored.first() {
+        // a deleted or expired row still occupies the unique key, so re-granting reuses it. It is
+        // found first, MySQL doesn't count a row an update leaves unchanged as affected
+        Some(row) if !is_active_model(row, unix_timestamp(SystemTime::now())) => {
+            Entity::update_many()
+                .col_expr(Column::ExpiresAt, Expr::value(expires_at))
+                .col_expr(Column::DeletedAt, Expr::value(Option::<i64>::None))
+                .col_expr(Column::Arity, Expr::value(rule.arity))
+                .filter(Column::Id.eq(row.id))
+                .exec(conn)
+                .await
+                .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))?;
+            telemetry::record("rows_affected", 1);
+
+            return Ok(true);
+        }
+        // the unique index doesn't compare `NULL` values, so it can't reject the duplicate
+        Some(_) if rule.nullable => {
+            return Err(CasbinError::from(AdapterError(Box::new(DbErr::Custom(
+                "the rule is already stored".to_owned(),
+            )))));
+        }
+        _ => {}
+    }
+
+    rule.to_active_model(expires_at)
         .insert(conn)
         .await
         .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))?;
+    telemetry::record("rows_affected", 1);
 
     Ok(true)
 }
 
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(backend = ?conn.get_database_backend(), ptype = rules.first().map_or("", |x| x.ptype), rows_affected = tracing::field::Empty)
+    )
+)]
+/// Returns the number of rules inserted or revived.
 pub(crate) async fn add_policies<'conn, 'rule, C: ConnectionTrait>(
     conn: &'conn C,
     rules: Vec<RuleWithType<'rule>>,
-) -> Result<bool> {
-    for rule in rules {
-        add_policy(conn, rule).await?;
+) -> Result<u64> {
+    let now = unix_timestamp(SystemTime::now());
+    let mut count = 0;
+
+    for chunk in rules.chunks(BATCH_SIZE) {
+        let stored = find_stored(conn, chunk).await?;
+        let stored = stored
+            .iter()
+            .map(|x| (stored_key(x), x))
+            .collect::<HashMap<_, _>>();
+
+        // the unique index doesn't compare `NULL` values, so it can't reject the duplicates
+        if chunk.iter().any(|x| x.nullable) {
+            let mut seen = HashSet::new();
+            let duplicate = chunk.iter().any(|x| {
+                let key = (x.ptype, x.columns());
+                stored.get(&key).is_some_and(|x| is_active_model(x, now)) || !seen.insert(key)
+            });
+
+            if duplicate {
+                return Err(CasbinError::from(AdapterError(Box::new(DbErr::Custom(
+                    "the rule is already stored".to_owned(),
+                )))));
+            }
+        }
+
+        // a deleted or expired row still occupies the unique key, so re-granting reuses it
+        let mut revived = Vec::new();
+        let mut inserted = Vec::new();
+
+        for rule in chunk {
+            match stored.get(&(rule.ptype, rule.columns())) {
+                Some(row) if !is_active_model(row, now) => revived.push((row.id, rule.arity)),
+                _ => inserted.push(rule),
+            }
+        }
+
+        count += revive_policies(conn, &revived).await?;
+        insert_policies(conn, &inserted).await?;
+        count += inserted.len() as u64;
     }
 
-    Ok(true)
+    telemetry::record("rows_affected", count);
+
+    Ok(count)
 }
 
-pub(crate) async fn clear_policy<C: ConnectionTrait>(conn: &C) -> Result<()> {
-    entity::Entity::delete_many()
-        .exec(conn)
+/// Finds the rows of the rules, whether they are deleted or expired or not.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(backend = ?conn.get_database_backend(), rules = tracing::field::Empty)
+    )
+)]
+async fn find_stored<C: ConnectionTrait>(
+    conn: &C,
+    rules: &[RuleWithType<'_>],
+) -> Result<Vec<entity::Model>> {
+    let stored = Entity::find()
+        .filter(
+            rules
+                .iter()
+                .map(exact_match)
+                .fold(Condition::any(), Condition::add),
+        )
+        .all(conn)
         .await
         .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))?;
+    telemetry::record("rules", stored.len() as u64);
+
+    Ok(stored)
+}
+
+fn stored_key(model: &entity::Model) -> (&str, [Option<&str>; 6]) {
+    let values = [
+        &model.v0, &model.v1, &model.v2, &model.v3, &model.v4, &model.v5,
+    ];
+
+    (model.ptype.as_str(), values.map(Option::as_deref))
+}
+
+fn is_active_model(model: &entity::Model, now: i64) -> bool {
+    model.deleted_at.is_none() && !matches!(model.expires_at, Some(x) if x <= now)
+}
+
+/// Revives the deleted or expired rows found by `find_stored`, given by id and the arity of the
+/// added rule, without an expiry.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(backend = ?conn.get_database_backend(), rows_affected = tracing::field::Empty)
+    )
+)]
+async fn revive_policies<C: ConnectionTrait>(conn: &C, rows: &[(i64, Option<i32>)]) -> Result<u64> {
+    let mut count = 0;
+
+    // revived rows take the arity of the added rule, so rows are revived by arity
+    let mut arities = rows.iter().map(|(_, arity)| *arity).collect::<Vec<_>>();
+    arities.sort_unstable();
+    arities.dedup();
+
+    for arity in arities {
+        let revived = Entity::update_many()
+            .col_expr(Column::ExpiresAt, Expr::value(Option::<i64>::None))
+            .col_expr(Column::DeletedAt, Expr::value(Option::<i64>::None))
+            .col_expr(Column::Arity, Expr::value(arity))
+            .filter(Column::Id.is_in(rows.iter().filter(|(_, x)| *x == arity).map(|(id, _)| *id)))
+            .filter(
+                Condition::any()
+                    .add(Column::DeletedAt.is_not_null())
+                    .add(Column::ExpiresAt.lte(unix_timestamp(SystemTime::now()))),
+            )
+            .exec(conn)
+            .await
+            .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))?;
+
+        count += revived.rows_affected;
+    }
+
+    telemetry::record("rows_affected", count);
+
+    Ok(count)
+}
+
+/// Inserts the rules in a single statement, a rule that is already stored fails it.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(backend = ?conn.get_database_backend(), rows_affected = tracing::field::Empty)
+    )
+)]
+async fn insert_policies<C: ConnectionTrait>(conn: &C, rules: &[&RuleWithType<'_>]) -> Result<()> {
+    if rules.is_empty() {
+        return Ok(());
+    }
+
+    Entity::insert_many(rules.iter().map(|x| x.to_active_model(None)))
+        .exec_without_returning(conn)
+        .await
+        .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))?;
+    telemetry::record("rows_affected", rules.len() as u64);
 
     Ok(())
 }
+
+/// Inserts the rules that are not stored yet and revives the deleted or expired ones.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(backend = ?conn.get_database_backend(), rules = rules.len(), rows_affected = tracing::field::Empty)
+    )
+)]
+pub(crate) async fn merge_policies<'conn, 'rule, C: ConnectionTrait>(
+    conn: &'conn C,
+    rules: Vec<RuleWithType<'rule>>,
+) -> Result<u64> {
+    let now = unix_timestamp(SystemTime::now());
+    let mut count = 0;
+
+    for chunk in rules.chunks(BATCH_SIZE) {
+        let stored = find_stored(conn, chunk).await?;
+        let stored = stored
+            .iter()
+            .map(|x| (stored_key(x), x))
+            .collect::<HashMap<_, _>>();
+
+        let mut revived = Vec::new();
+        let mut inserted = Vec::new();
+        let mut seen = HashSet::new();
+
+        // rules are counted from the stored rows, MySQL reports the ignored duplicates as affected
+        // rows, and `ON CONFLICT` doesn't see duplicates with `NULL` values
+        for rule in chunk {
+            let key = (rule.ptype, rule.columns());
+            if !seen.insert(key) {
+                continue;
+            }
+
+            match stored.get(&key) {
+                Some(row) if is_active_model(row, now) => {}
+                Some(row) => revived.push((row.id, rule.arity)),
+                None => inserted.push(rule),
+            }
+        }
+
+        count += revive_policies(conn, &revived).await?;
+
+        if inserted.is_empty() {
+            continue;
+        }
+
+        // the conflict target must be the columns of the unique index
+        let mut on_conflict = if chunk.iter().any(|x| x.hashed) {
+            OnConflict::column(Column::RuleHash)
+        } else {
+            OnConflict::columns([
+                Column::Ptype,
+                Column::V0,
+                Column::V1,
+                Column::V2,
+                Column::V3,
+                Column::V4,
+                Column::V5,
+            ])
+        };
+
+        // a concurrent writer may have stored some of them since
+        Entity::insert_many(inserted.iter().map(|x| x.to_active_model(None)))
+            .on_conflict(on_conflict.do_nothing_on([Column::Id]).to_owned())
+            .exec_without_returning(conn)
+            .await
+            .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))?;
+
+        count += inserted.len() as u64;
+    }
+
+    telemetry::record("rows_affected", count);
+
+    Ok(count)
+}
+
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(backend = ?conn.get_database_backend(), rows_affected = tracing::field::Empty)
+    )
+)]
+pub(crate) async fn purge_expired<C: ConnectionTrait>(
+    conn: &C,
+    soft_delete: bool,
+) -> Result<Vec<entity::Model>> {
+    let now = unix_timestamp(SystemTime::now());
+
+    let expired = Entity::find()
+        .filter(Column::DeletedAt.is_null())
+        .filter(Column::ExpiresAt.lte(now))
+        .all(conn)
+        .await
+        .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))?;
+
+    if expired.is_empty() {
+        return Ok(expired);
+    }
+
+    let removed = delete_where(
+        conn,
+        soft_delete,
+        Condition::all()
+            .add(Column::Id.is_in(expired.iter().map(|x| x.id)))
+            .add(Column::ExpiresAt.lte(now)),
+    )
+    .await?;
+    telemetry::record("rows_affected", removed);
+
+    Ok(expired)
+}
+
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(backend = ?conn.get_database_backend(), ptype = rule.ptype, rows_affected = tracing::field::Empty)
+    )
+)]
+pub(crate) async fn restore_policy<'conn, 'rule, C: ConnectionTrait>(
+    conn: &'conn C,
+    rule: RuleWithType<'rule>,
+) -> Result<bool> {
+    let restored = Entity::update_many()
+        .col_expr(Column::DeletedAt, Expr::value(Option::<i64>::None))
+        .filter(exact_match(&rule))
+        .filter(Column::DeletedAt.is_not_null())
+        .exec(conn)
+        .await
+        .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))?;
+    telemetry::record("rows_affected", restored.rows_affected);
+
+    Ok(restored.rows_affected == 1)
+}
+
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(backend = ?conn.get_database_backend(), rules = tracing::field::Empty)
+    )
+)]
+pub(crate) async fn list_deleted<C: ConnectionTrait>(conn: &C) -> Result<Vec<entity::Model>> {
+    let rules = Entity::find()
+        .filter(Column::DeletedAt.is_not_null())
+        .all(conn)
+        .await
+        .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))?;
+    telemetry::record("rules", rules.len() as u64);
+
+    Ok(rules)
+}
+
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(backend = ?conn.get_database_backend(), rows_affected = tracing::field::Empty)
+    )
+)]
+/// Returns the number of rules removed.
+pub(crate) async fn clear_policy<C: ConnectionTrait>(conn: &C, soft_delete: bool) -> Result<u64> {
+    let removed = delete_where(conn, soft_delete, Condition::all()).await?;
+    telemetry::record("rows_affected", removed);
+
+    Ok(removed)
+}
@@ -0,0 +1,140 @@
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+use crate::{Error, PolicyRule};
+
+// same as `casbin::util::parse_csv_line`, which is not public
+fn field() -> &'static Regex {
+    static FIELD: OnceLock<Regex> = OnceLock::new();
+
+    FIELD.get_or_init(|| Regex::new(r#"(\s*"[^"]*"?|\s*[^,]*)"#).unwrap())
+}
+
+pub(crate) fn parse_line(line: &str) -> Option<Vec<String>> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+
+    let fields = field()
+        .find_iter(line)
+        .map(|m| {
+            let field = m.as_str().trim();
+            if field.len() >= 2 && field.starts_with('"') && field.ends_with('"') {
+                field[1..field.len() - 1].to_owned()
+            } else {
+                field.to_owned()
+            }
+        })
+        .collect::<Vec<_>>();
+
+    (!fields.is_empty()).then_some(fields)
+}
+
+/// Parses a `ptype, v0, v1, ...` line, blank lines and comments are `None`.
+pub(crate) fn parse_rule(line: &str, line_no: usize) -> Result<Option<PolicyRule>, Error> {
+    let Some(mut fields) = parse_line(line) else {
+        return Ok(None);
+    };
+
+    let ptype = fields.remove(0);
+    let rule = PolicyRule {
+        ptype,
+        values: fields,
+    };
+
+    rule.check().map_err(|reason| Error::InvalidRule {
+        line: line_no,
+        reason,
+    })?;
+
+    Ok(Some(rule))
+}
+
+pub(crate) fn format_rule(rule: &PolicyRule, line_no: usize) -> Result<String, Error> {
+    let mut line = escape_field(&rule.ptype, line_no)?;
+
+    for value in &rule.values {
+        line.push_str(", ");
+        line.push_str(&escape_field(value, line_no)?);
+    }
+
+    Ok(line)
+}
+
+// casbin has no escape sequence, values are quoted when they would be split or trimmed otherwise,
+// and values that can't be parsed back are rejected
+fn escape_field(field: &str, line_no: usize) -> Result<String, Error> {
+    if field.contains(['\n', '\r'])
+        || (field.contains('"') && (needs_quotes(field) || field.starts_with('"')))
+    {
+        return Err(Error::InvalidRule {
+            line: line_no,
+            reason: format!("value `{field}` can't be written in casbin's CSV format"),
+        });
+    }
+
+    Ok(quote_field(field))
+}
+
+pub(crate) fn quote_field(field: &str) -> String {
+    if needs_quotes(field) {
+        format!("\"{field}\"")
+    } else {
+        field.to_owned()
+    }
+}
+
+fn needs_quotes(field: &str) -> bool {
+    field.contains(',') || field.trim() != field
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(ptype: &str, values: &[&str]) -> PolicyRule {
+        PolicyRule {
+            ptype: ptype.to_owned(),
+            values: values.iter().map(|x| x.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_parse_rule() {
+        assert_eq!(
+            parse_rule("p, alice, \"domain1, domain2\", data1 , read", 1).unwrap(),
+            Some(rule("p", &["alice", "domain1, domain2", "data1", "read"]))
+        );
+        assert_eq!(
+            parse_rule("p, alice, , read", 1).unwrap(),
+            Some(rule("p", &["alice", "", "read"]))
+        );
+        assert_eq!(parse_rule("  # comment", 1).unwrap(), None);
+        assert_eq!(parse_rule("", 1).unwrap(), None);
+        assert!(matches!(
+            parse_rule("p", 3),
+            Err(Error::InvalidRule { line: 3, .. })
+        ));
+        assert!(parse_rule("p, 1, 2, 3, 4, 5, 6, 7", 1).is_err());
+    }
+
+    #[test]
+    fn test_format_rule() {
+        let rules = [
+            rule("p", &["alice", "data1", "read"]),
+            rule("p", &["alice", "", "read"]),
+            rule("p", &["alice", "a, b", " padded "]),
+            rule("g", &["say \"hi\"", "data2_admin"]),
+        ];
+
+        for rule in rules {
+            let line = format_rule(&rule, 1).unwrap();
+            assert_eq!(parse_rule(&line, 1).unwrap(), Some(rule));
+        }
+
+        assert!(format_rule(&rule("p", &["\"quoted\""]), 1).is_err());
+        assert!(format_rule(&rule("p", &["a, \"b\""]), 1).is_err());
+    }
+}
@@ -0,0 +1,27 @@
+use std::collections::BTreeSet;
+
+use crate::PolicyRule;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PolicyDiff {
+    /// Rules only present in the newer rule set.
+    pub added: Vec<PolicyRule>,
+    /// Rules only present in the older rule set.
+    pub removed: Vec<PolicyRule>,
+}
+
+impl PolicyDiff {
+    pub(crate) fn between(old: Vec<PolicyRule>, new: Vec<PolicyRule>) -> Self {
+        let old = old.into_iter().collect::<BTreeSet<_>>();
+        let new = new.into_iter().collect::<BTreeSet<_>>();
+
+        PolicyDiff {
+            added: new.difference(&old).cloned().collect(),
+            removed: old.difference(&new).cloned().collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
@@ -1,5 +1,10 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.11.2
 
+pub mod model;
+pub mod revision;
+pub mod snapshot;
+pub mod snapshot_rule;
+
 use sea_orm::entity::prelude::*;
 
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
@@ -8,12 +13,16 @@ pub struct Model {
     #[sea_orm(primary_key)]
     pub id: i64,
     pub ptype: String,
-    pub v0: String,
-    pub v1: String,
-    pub v2: String,
-    pub v3: String,
-    pub v4: String,
-    pub v5: String,
+    pub v0: Option<String>,
+    pub v1: Option<String>,
+    pub v2: Option<String>,
+    pub v3: Option<String>,
+    pub v4: Option<String>,
+    pub v5: Option<String>,
+    pub expires_at: Option<i64>,
+    pub deleted_at: Option<i64>,
+    pub arity: Option<i32>,
+    pub rule_hash: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -0,0 +1,23 @@
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
+#[sea_orm(table_name = "casbin_snapshot_rule")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub snapshot_id: i64,
+    pub ptype: String,
+    pub v0: Option<String>,
+    pub v1: Option<String>,
+    pub v2: Option<String>,
+    pub v3: Option<String>,
+    pub v4: Option<String>,
+    pub v5: Option<String>,
+    pub expires_at: Option<i64>,
+    pub arity: Option<i32>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
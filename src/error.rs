@@ -0,0 +1,96 @@
+use std::{fmt, time::Duration};
+
+use crate::{LoadReport, SchemaDifference};
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    SnapshotNotFound(String),
+    /// `version` is `None` when the latest version was requested.
+    ModelNotFound {
+        name: String,
+        version: Option<i64>,
+    },
+    /// A rule that can't be parsed, or can't be written as CSV, `line` starts from 1, or is 0 when
+    /// unknown.
+    InvalidRule {
+        line: usize,
+        reason: String,
+    },
+    /// Returned by loads in strict mode, the model is left untouched.
+    RulesSkipped(LoadReport),
+    /// A cursor that was not returned by `query_policies`, or used with another sort key.
+    InvalidCursor(String),
+    /// A column of an existing table doesn't use the collation of `SchemaOptions::mysql_collation`.
+    CollationMismatch {
+        column: String,
+        expected: String,
+        found: Option<String>,
+    },
+    /// Returned by `SeaOrmAdapter::new_with_options` with `AdapterOptions::verify_schema`.
+    SchemaMismatch(Vec<SchemaDifference>),
+    /// Another instance held the migration lock for longer than `AdapterOptions::migration_lock_timeout`.
+    MigrationLockTimeout(Duration),
+    /// Returned by `save_policy` when another writer saved the rules since they were loaded,
+    /// `expected` is the revision of the last load and `found` the current one.
+    Conflict {
+        expected: i64,
+        found: i64,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::SnapshotNotFound(name) => write!(f, "snapshot `{name}` not found"),
+            Error::ModelNotFound {
+                name,
+                version: Some(version),
+            } => write!(f, "model `{name}` version {version} not found"),
+            Error::ModelNotFound {
+                name,
+                version: None,
+            } => write!(f, "model `{name}` not found"),
+            Error::InvalidRule { line, reason } => {
+                write!(f, "invalid rule at line {line}: {reason}")
+            }
+            Error::RulesSkipped(report) => write!(f, "strict load failed, {report}"),
+            Error::InvalidCursor(cursor) => write!(f, "invalid cursor `{cursor}`"),
+            Error::CollationMismatch {
+                column,
+                expected,
+                found: Some(found),
+            } => write!(
+                f,
+                "column `{column}` uses collation `{found}` instead of `{expected}`"
+            ),
+            Error::CollationMismatch {
+                column,
+                expected,
+                found: None,
+            } => write!(
+                f,
+                "column `{column}` has no collation, expected `{expected}`"
+            ),
+            Error::SchemaMismatch(differences) => {
+                f.write_str("unexpected `casbin_rule` schema")?;
+
+                for (index, difference) in differences.iter().enumerate() {
+                    let separator = if index == 0 { ": " } else { ", " };
+                    write!(f, "{separator}{difference}")?;
+                }
+
+                Ok(())
+            }
+            Error::MigrationLockTimeout(timeout) => {
+                write!(f, "migration lock not acquired within {timeout:?}")
+            }
+            Error::Conflict { expected, found } => write!(
+                f,
+                "the rules were saved by another writer, revision {found} instead of {expected}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
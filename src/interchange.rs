@@ -0,0 +1,187 @@
+use std::io::{self, Read, Write};
+
+use serde::{de::Error as _, Deserialize, Deserializer};
+
+use crate::{Error, PolicyRule};
+
+// checked while deserializing each field, so that the error carries the position of the rule
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CheckedRule {
+    #[serde(deserialize_with = "deserialize_ptype")]
+    ptype: String,
+    #[serde(deserialize_with = "deserialize_values")]
+    values: Vec<String>,
+}
+
+fn deserialize_ptype<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    let ptype = String::deserialize(deserializer)?;
+
+    if ptype.is_empty() {
+        return Err(D::Error::custom("ptype is empty"));
+    }
+
+    Ok(ptype)
+}
+
+fn deserialize_values<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    let values = Vec::<String>::deserialize(deserializer)?;
+
+    if values.is_empty() || values.len() > 6 {
+        return Err(D::Error::custom(format!(
+            "a rule has 1 to 6 values, found {}",
+            values.len()
+        )));
+    }
+
+    Ok(values)
+}
+
+impl From<CheckedRule> for PolicyRule {
+    fn from(value: CheckedRule) -> Self {
+        PolicyRule {
+            ptype: value.ptype,
+            values: value.values,
+        }
+    }
+}
+
+pub(crate) fn read_json<R: Read>(reader: R) -> Result<Vec<PolicyRule>, Error> {
+    serde_json::from_reader::<_, Vec<CheckedRule>>(reader)
+        .map(|rules| rules.into_iter().map(PolicyRule::from).collect())
+        .map_err(|err| Error::InvalidRule {
+            line: err.line(),
+            reason: err.to_string(),
+        })
+}
+
+// rules always serialize, so the writer is the only source of errors
+pub(crate) fn write_json<W: Write>(writer: W, rules: &[PolicyRule]) -> io::Result<()> {
+    serde_json::to_writer_pretty(writer, rules).map_err(io::Error::from)
+}
+
+pub(crate) fn read_yaml<R: Read>(reader: R) -> Result<Vec<PolicyRule>, Error> {
+    serde_yaml::from_reader::<_, Vec<CheckedRule>>(reader)
+        .map(|rules| rules.into_iter().map(PolicyRule::from).collect())
+        .map_err(|err| Error::InvalidRule {
+            line: err.location().map_or(0, |x| x.line()),
+            reason: err.to_string(),
+        })
+}
+
+// serde_yaml doesn't expose the error of the writer, so it is kept aside
+struct KeepError<W> {
+    writer: W,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> Write for KeepError<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer.write(buf).map_err(|err| {
+            let kind = err.kind();
+            self.error = Some(err);
+            kind.into()
+        })
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush().map_err(|err| {
+            let kind = err.kind();
+            self.error = Some(err);
+            kind.into()
+        })
+    }
+}
+
+pub(crate) fn write_yaml<W: Write>(writer: W, rules: &[PolicyRule]) -> io::Result<()> {
+    let mut writer = KeepError {
+        writer,
+        error: None,
+    };
+
+    serde_yaml::to_writer(&mut writer, rules)
+        .map_err(|err| writer.error.take().unwrap_or_else(|| io::Error::other(err)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules() -> Vec<PolicyRule> {
+        vec![
+            PolicyRule {
+                ptype: "p".to_owned(),
+                values: vec!["alice".to_owned(), "".to_owned(), "read".to_owned()],
+            },
+            PolicyRule {
+                ptype: "g".to_owned(),
+                values: vec!["alice".to_owned(), "data2_admin".to_owned()],
+            },
+        ]
+    }
+
+    struct BrokenPipe;
+
+    impl Write for BrokenPipe {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_write_error() {
+        let err = write_json(BrokenPipe, &rules()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+
+        let err = write_yaml(BrokenPipe, &rules()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+    }
+
+    #[test]
+    fn test_json() {
+        let mut buf = Vec::new();
+        write_json(&mut buf, &rules()).unwrap();
+        assert_eq!(read_json(buf.as_slice()).unwrap(), rules());
+
+        let input = r#"[
+  { "ptype": "p", "values": ["alice", "data1", "read"] },
+  { "ptype": "p", "values": [] }
+]"#;
+        assert!(matches!(
+            read_json(input.as_bytes()),
+            Err(Error::InvalidRule { line: 3, .. })
+        ));
+    }
+
+    #[test]
+    fn test_yaml() {
+        let mut buf = Vec::new();
+        write_yaml(&mut buf, &rules()).unwrap();
+        assert_eq!(read_yaml(buf.as_slice()).unwrap(), rules());
+
+        let input = "\
+- ptype: p
+  values: [alice, data1, read]
+- ptype: p
+  values: []
+";
+        assert!(matches!(
+            read_yaml(input.as_bytes()),
+            Err(Error::InvalidRule { line: 3, .. })
+        ));
+
+        let input = "\
+- ptype: p
+  values: [alice, data1, read]
+  extra: 1
+";
+        assert!(matches!(
+            read_yaml(input.as_bytes()),
+            Err(Error::InvalidRule { line: 3, .. })
+        ));
+    }
+}
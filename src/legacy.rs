@@ -0,0 +1,352 @@
+use std::fmt;
+
+use sea_orm::{
+    sea_query::{Asterisk, Expr, OnConflict, Query, Table},
+    ColumnTrait, ConnectionTrait, DatabaseBackend, DbErr, EntityTrait, Order, QueryFilter,
+    Statement, TransactionTrait,
+};
+
+use crate::{
+    action::{self, Rule, RuleWithType, BATCH_SIZE},
+    entity::{Column, Entity},
+    migration, SchemaOptions,
+};
+
+// the lengths of the `casbin_rule` columns created by `up`, values are `TEXT` with
+// `SchemaOptions::hashed_values`
+const PTYPE_LEN: usize = 18;
+const VALUE_LEN: usize = 125;
+
+/// An adapter whose `casbin_rule` table can be migrated by `migrate_legacy`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LegacyAdapter {
+    /// `sqlx-adapter`, detected by its `unique_key_sqlx_adapter` constraint.
+    Sqlx,
+    /// `diesel-adapter`, detected by its `unique_key_diesel_adapter` constraint.
+    Diesel,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LegacyMode {
+    /// Replaces the legacy table, nothing is changed if a rule does not fit in the new table.
+    InPlace,
+    /// Copies the legacy table to `table`, then replaces it like `InPlace`, but without the rules
+    /// that don't fit.
+    Backup { table: String },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LegacyReport {
+    pub adapter: LegacyAdapter,
+    /// Number of rules written to the new table.
+    pub copied: u64,
+    /// Rules of the legacy table that were not copied.
+    pub conflicts: Vec<LegacyConflict>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LegacyConflict {
+    /// Primary key of the row in the legacy table.
+    pub id: i64,
+    pub ptype: String,
+    pub reason: ConflictReason,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConflictReason {
+    /// The value has more characters than the column of the new table allows.
+    TooLong {
+        column: &'static str,
+        length: usize,
+        max: usize,
+    },
+    /// The rule is equal to a rule copied before under the unique key of the new table, e.g. with
+    /// a case-insensitive collation, or one ignoring trailing spaces.
+    Duplicate,
+}
+
+impl fmt::Display for ConflictReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConflictReason::TooLong {
+                column,
+                length,
+                max,
+            } => write!(f, "`{column}` has {length} characters, at most {max} fit"),
+            ConflictReason::Duplicate => f.write_str("equal to another rule in the new table"),
+        }
+    }
+}
+
+/// Returns the adapter that created `casbin_rule`, `None` if it's not a known legacy table.
+pub async fn detect_legacy<C: ConnectionTrait>(conn: &C) -> Result<Option<LegacyAdapter>, DbErr> {
+    if has_unique_key(conn, "unique_key_sqlx_adapter").await? {
+        Ok(Some(LegacyAdapter::Sqlx))
+    } else if has_unique_key(conn, "unique_key_diesel_adapter").await? {
+        Ok(Some(LegacyAdapter::Diesel))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Moves the rules of a `casbin_rule` table created by `sqlx-adapter` or `diesel-adapter` into the
+/// schema that `up_with_options` creates with `options`, in a single transaction.
+///
+/// Rules that don't fit are reported in `LegacyReport::conflicts`. MySQL commits DDL implicitly,
+/// so a failure there, or a duplicate found with `LegacyMode::InPlace`, can leave the legacy table
+/// dropped, use `LegacyMode::Backup` to keep it.
+pub async fn migrate_legacy<C: ConnectionTrait + TransactionTrait>(
+    conn: &C,
+    mode: LegacyMode,
+    options: &SchemaOptions,
+) -> Result<LegacyReport, DbErr> {
+    let Some(adapter) = detect_legacy(conn).await? else {
+        return Err(DbErr::Custom(
+            "`casbin_rule` was not created by sqlx-adapter or diesel-adapter".to_owned(),
+        ));
+    };
+
+    let txn = conn.begin().await?;
+    let builder = txn.get_database_backend();
+
+    let rows = load_legacy_rules(&txn).await?;
+
+    let mut report = LegacyReport {
+        adapter,
+        copied: 0,
+        conflicts: Vec::new(),
+    };
+    let mut fitting = Vec::with_capacity(rows.len());
+
+    for (id, ptype, values) in rows {
+        match check_lengths(&ptype, &values, options) {
+            Some(reason) => report.conflicts.push(LegacyConflict { id, ptype, reason }),
+            None => fitting.push((id, ptype, values)),
+        }
+    }
+
+    // the conflicting rules would be lost with the legacy table
+    if mode == LegacyMode::InPlace && !report.conflicts.is_empty() {
+        return Ok(report);
+    }
+
+    if let LegacyMode::Backup { table } = &mode {
+        let select = Query::select().column(Asterisk).from(Entity).to_owned();
+        let sql = format!(
+            "CREATE TABLE {} AS {}",
+            migration::quote(builder, table),
+            builder.build(&select).sql
+        );
+
+        txn.execute(Statement::from_string(builder, sql)).await?;
+    }
+
+    let drop_table = Table::drop().table(Entity).to_owned();
+    txn.execute(builder.build(&drop_table)).await?;
+
+    migration::up_with_options(&txn, options).await?;
+
+    for chunk in fitting.chunks(BATCH_SIZE) {
+        report.copied += copy_rules(&txn, chunk, options, &mut report.conflicts).await?;
+    }
+
+    report.conflicts.sort_by_key(|x| x.id);
+
+    // the duplicates would be lost with the legacy table too, rolled back when `txn` is dropped
+    if mode == LegacyMode::InPlace && !report.conflicts.is_empty() {
+        return Ok(report);
+    }
+
+    txn.commit().await?;
+
+    Ok(report)
+}
+
+fn to_rule<'a>(ptype: &'a str, values: &'a [String], options: &SchemaOptions) -> RuleWithType<'a> {
+    let mut rule = RuleWithType::from_rule(ptype, Rule::from_string(values));
+    rule.nullable = options.nullable_values;
+    rule.hashed = options.hashed_values;
+
+    rule
+}
+
+// the new unique key can consider rules equal that the legacy one didn't, e.g. with another
+// collation, so when the table has less rows than copied, the rules are copied again one by one to
+// find the duplicates. The affected rows can't tell, MySQL counts the ignored duplicates too
+async fn copy_rules<C: ConnectionTrait>(
+    conn: &C,
+    rules: &[(i64, String, Vec<String>)],
+    options: &SchemaOptions,
+    conflicts: &mut Vec<LegacyConflict>,
+) -> Result<u64, DbErr> {
+    let before = max_id(conn).await?;
+    let count = migration::count_rows(conn, "casbin_rule").await?;
+
+    // the conflict target must be the columns of the unique index
+    let mut on_conflict = if options.hashed_values {
+        OnConflict::column(Column::RuleHash)
+    } else {
+        OnConflict::columns([
+            Column::Ptype,
+            Column::V0,
+            Column::V1,
+            Column::V2,
+            Column::V3,
+            Column::V4,
+            Column::V5,
+        ])
+    };
+
+    Entity::insert_many(
+        rules
+            .iter()
+            .map(|(_, ptype, values)| to_rule(ptype, values, options).to_active_model(None)),
+    )
+    .on_conflict(on_conflict.do_nothing_on([Column::Id]).to_owned())
+    .exec_without_returning(conn)
+    .await?;
+
+    if migration::count_rows(conn, "casbin_rule").await? - count == rules.len() as i64 {
+        return Ok(rules.len() as u64);
+    }
+
+    Entity::delete_many()
+        .filter(Column::Id.gt(before))
+        .exec(conn)
+        .await?;
+
+    let mut copied = 0;
+
+    for (id, ptype, values) in rules {
+        let rule = to_rule(ptype, values, options);
+
+        // compared by the database, like the unique key
+        if Entity::find()
+            .filter(action::exact_match(&rule))
+            .one(conn)
+            .await?
+            .is_some()
+        {
+            conflicts.push(LegacyConflict {
+                id: *id,
+                ptype: ptype.clone(),
+                reason: ConflictReason::Duplicate,
+            });
+            continue;
+        }
+
+        Entity::insert(rule.to_active_model(None))
+            .exec_without_returning(conn)
+            .await?;
+        copied += 1;
+    }
+
+    Ok(copied)
+}
+
+async fn max_id<C: ConnectionTrait>(conn: &C) -> Result<i64, DbErr> {
+    let builder = conn.get_database_backend();
+    let select = Query::select()
+        .expr(Expr::col(Column::Id).max())
+        .from(Entity)
+        .to_owned();
+
+    match conn.query_one(builder.build(&select)).await? {
+        Some(row) => Ok(row.try_get_by_index::<Option<i64>>(0)?.unwrap_or_default()),
+        None => Ok(0),
+    }
+}
+
+async fn load_legacy_rules<C: ConnectionTrait>(
+    conn: &C,
+) -> Result<Vec<(i64, String, Vec<String>)>, DbErr> {
+    let select = Query::select()
+        .columns([
+            Column::Id,
+            Column::Ptype,
+            Column::V0,
+            Column::V1,
+            Column::V2,
+            Column::V3,
+            Column::V4,
+            Column::V5,
+        ])
+        .from(Entity)
+        .order_by(Column::Id, Order::Asc)
+        .to_owned();
+
+    let builder = conn.get_database_backend();
+    let mut rules = Vec::new();
+
+    for row in conn.query_all(builder.build(&select)).await? {
+        // both adapters use a 32-bit `id`, which Postgres won't decode as `i64`
+        let id = match row.try_get::<i64>("", "id") {
+            Ok(id) => id,
+            Err(_) => row.try_get::<i32>("", "id")?.into(),
+        };
+
+        let values = ["v0", "v1", "v2", "v3", "v4", "v5"]
+            .into_iter()
+            .map(|column| row.try_get::<String>("", column))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        rules.push((id, row.try_get("", "ptype")?, values));
+    }
+
+    Ok(rules)
+}
+
+fn check_lengths(
+    ptype: &str,
+    values: &[String],
+    options: &SchemaOptions,
+) -> Option<ConflictReason> {
+    let max = if options.hashed_values {
+        usize::MAX
+    } else {
+        VALUE_LEN
+    };
+    let columns = ["v0", "v1", "v2", "v3", "v4", "v5"]
+        .into_iter()
+        .zip(values)
+        .map(|(column, value)| (column, value.as_str(), max));
+
+    std::iter::once(("ptype", ptype, PTYPE_LEN))
+        .chain(columns)
+        .find_map(|(column, value, max)| {
+            let length = value.chars().count();
+
+            (length > max).then_some(ConflictReason::TooLong {
+                column,
+                length,
+                max,
+            })
+        })
+}
+
+async fn has_unique_key<C: ConnectionTrait>(conn: &C, name: &str) -> Result<bool, DbErr> {
+    let backend = conn.get_database_backend();
+
+    match backend {
+        // a `UNIQUE` constraint is backed by an index of the same name
+        DatabaseBackend::MySql | DatabaseBackend::Postgres => {
+            migration::has_index(conn, "casbin_rule", name).await
+        }
+        // but SQLite names it `sqlite_autoindex_*`, so look for the constraint in the table definition
+        DatabaseBackend::Sqlite => {
+            let stmt = Statement::from_sql_and_values(
+                backend,
+                "SELECT 1 FROM sqlite_master WHERE tbl_name = ? AND (name = ? OR sql LIKE ?)",
+                [
+                    "casbin_rule".into(),
+                    name.into(),
+                    format!("%CONSTRAINT%{name}%UNIQUE%").into(),
+                ],
+            );
+
+            conn.query_one(stmt).await.map(|row| row.is_some())
+        }
+    }
+}
@@ -2,8 +2,38 @@
 
 mod action;
 mod adapter;
+mod csv;
+mod diff;
 pub mod entity;
+mod error;
+#[cfg(feature = "serde")]
+mod interchange;
+mod legacy;
+mod lock;
 mod migration;
+mod model_store;
+mod options;
+mod query;
+mod report;
+mod rule;
+mod schema;
+mod snapshot;
+mod telemetry;
 
 pub use adapter::SeaOrmAdapter;
-pub use migration::{down, up};
+pub use diff::PolicyDiff;
+pub use error::Error;
+pub use legacy::{
+    detect_legacy, migrate_legacy, ConflictReason, LegacyAdapter, LegacyConflict, LegacyMode,
+    LegacyReport,
+};
+pub use migration::{
+    down, down_guarded, down_sql, up, up_sql, up_with_options, upgrade_sql, DownError, DownMode,
+    DownReport,
+};
+pub use options::{AdapterOptions, ImportMode, SchemaOptions};
+pub use query::{PolicyCursor, PolicyPage, PolicyQuery, SortKey};
+pub use report::{LoadReport, SkipReason, SkippedRule};
+pub use rule::PolicyRule;
+pub use schema::{verify_schema, ColumnKind, SchemaDifference};
+pub use snapshot::Snapshot;
@@ -0,0 +1,184 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use sea_orm::{
+    sea_query::{Alias, ColumnDef, OnConflict, Query, Table},
+    sqlx, ConnectionTrait, DatabaseBackend, DatabaseTransaction, DbErr, RuntimeErr, Statement,
+    TransactionTrait,
+};
+
+const LOCK_NAME: &str = "sea_orm_adapter_migration";
+
+// `pg_advisory_xact_lock` takes a number, these are the ASCII codes of `casbin`
+const PG_LOCK_KEY: i64 = 0x6361_7362_696e;
+
+pub(crate) const LOCK_TABLE: &str = "casbin_migration_lock";
+
+/// Begins a transaction holding the migration lock, `None` if it's still held by another
+/// connection after `timeout`.
+///
+/// Postgres takes a transaction-level advisory lock and MySQL a named lock of the connection. SQLite
+/// writes a row of `casbin_migration_lock`, which holds the write lock of the database until the
+/// transaction ends. The transaction must be ended with `release`.
+pub(crate) async fn acquire<C: ConnectionTrait + TransactionTrait>(
+    conn: &C,
+    timeout: Duration,
+) -> Result<Option<DatabaseTransaction>, DbErr> {
+    let backend = conn.get_database_backend();
+
+    // the row must be the first write of the transaction, SQLite doesn't wait for the write lock
+    // of a transaction that already read the database
+    if backend == DatabaseBackend::Sqlite && !has_lock_table(conn).await? {
+        let create_table = Table::create()
+            .if_not_exists()
+            .table(Alias::new(LOCK_TABLE))
+            .col(
+                ColumnDef::new(Alias::new("name"))
+                    .string_len(64)
+                    .not_null()
+                    .primary_key(),
+            )
+            .col(
+                ColumnDef::new(Alias::new("locked_at"))
+                    .big_integer()
+                    .not_null(),
+            )
+            .to_owned();
+
+        // creating the table is a write, it waits for the lock held by another instance
+        match conn.execute(backend.build(&create_table)).await {
+            Ok(_) => {}
+            Err(err) if is_lock_timeout(backend, &err) => return Ok(None),
+            Err(err) => return Err(err),
+        }
+    }
+
+    let txn = conn.begin().await?;
+
+    let locked = match backend {
+        DatabaseBackend::Postgres => {
+            // an integer is in milliseconds, `0` would wait forever
+            let sql = format!("SET LOCAL lock_timeout = {}", timeout.as_millis().max(1));
+            txn.execute_unprepared(&sql).await?;
+
+            let sql = format!("SELECT pg_advisory_xact_lock({PG_LOCK_KEY})");
+            match txn.execute_unprepared(&sql).await {
+                Ok(_) => {
+                    txn.execute_unprepared("SET LOCAL lock_timeout = DEFAULT")
+                        .await?;
+                    true
+                }
+                Err(err) if is_lock_timeout(backend, &err) => false,
+                Err(err) => return Err(err),
+            }
+        }
+        DatabaseBackend::MySql => {
+            // whole seconds, rounded up
+            let seconds = timeout.as_secs() + u64::from(timeout.subsec_nanos() > 0);
+            let stmt = Statement::from_sql_and_values(
+                backend,
+                "SELECT GET_LOCK(?, ?)",
+                [LOCK_NAME.into(), seconds.into()],
+            );
+
+            // `0` on timeout, `NULL` on errors such as a killed query
+            match txn.query_one(stmt).await? {
+                Some(row) => row.try_get_by_index::<Option<i64>>(0)? == Some(1),
+                None => false,
+            }
+        }
+        DatabaseBackend::Sqlite => {
+            let previous = match txn
+                .query_one(Statement::from_string(backend, "PRAGMA busy_timeout"))
+                .await?
+            {
+                Some(row) => row.try_get_by_index::<i64>(0)?,
+                None => 0,
+            };
+
+            let sql = format!("PRAGMA busy_timeout = {}", timeout.as_millis());
+            txn.execute_unprepared(&sql).await?;
+
+            let locked_at = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |duration| duration.as_secs() as i64);
+            let insert = Query::insert()
+                .into_table(Alias::new(LOCK_TABLE))
+                .columns([Alias::new("name"), Alias::new("locked_at")])
+                .values_panic([LOCK_NAME.into(), locked_at.into()])
+                .on_conflict(
+                    OnConflict::column(Alias::new("name"))
+                        .update_column(Alias::new("locked_at"))
+                        .to_owned(),
+                )
+                .to_owned();
+            let result = txn.execute(backend.build(&insert)).await;
+
+            // the connection goes back to the pool
+            let sql = format!("PRAGMA busy_timeout = {previous}");
+            txn.execute_unprepared(&sql).await?;
+
+            match result {
+                Ok(_) => true,
+                Err(err) if is_lock_timeout(backend, &err) => false,
+                Err(err) => return Err(err),
+            }
+        }
+    };
+
+    if locked {
+        Ok(Some(txn))
+    } else {
+        txn.rollback().await?;
+        Ok(None)
+    }
+}
+
+/// Releases the migration lock, and commits the transaction if `commit` is set or rolls it back.
+pub(crate) async fn release(txn: DatabaseTransaction, commit: bool) -> Result<(), DbErr> {
+    let backend = txn.get_database_backend();
+
+    // the lock of the connection would outlive the transaction
+    let released = if backend == DatabaseBackend::MySql {
+        let stmt =
+            Statement::from_sql_and_values(backend, "SELECT RELEASE_LOCK(?)", [LOCK_NAME.into()]);
+        txn.query_one(stmt).await.map(|_| ())
+    } else {
+        Ok(())
+    };
+
+    // the transaction is ended even if the lock couldn't be released
+    let ended = if commit {
+        txn.commit().await
+    } else {
+        txn.rollback().await
+    };
+
+    released.and(ended)
+}
+
+// a read, unlike `CREATE TABLE IF NOT EXISTS` on a connection that hasn't seen the table yet
+async fn has_lock_table<C: ConnectionTrait>(conn: &C) -> Result<bool, DbErr> {
+    let stmt = Statement::from_sql_and_values(
+        DatabaseBackend::Sqlite,
+        "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?",
+        [LOCK_TABLE.into()],
+    );
+
+    conn.query_one(stmt).await.map(|row| row.is_some())
+}
+
+fn is_lock_timeout(backend: DatabaseBackend, err: &DbErr) -> bool {
+    let (DbErr::Exec(RuntimeErr::SqlxError(sqlx::Error::Database(err)))
+    | DbErr::Query(RuntimeErr::SqlxError(sqlx::Error::Database(err)))) = err
+    else {
+        return false;
+    };
+    let code = err.code().unwrap_or_default();
+
+    match backend {
+        DatabaseBackend::Postgres => code == "55P03",
+        // `SQLITE_BUSY` and its extended codes
+        DatabaseBackend::Sqlite => code.parse::<i32>().is_ok_and(|code| code & 0xff == 5),
+        DatabaseBackend::MySql => false,
+    }
+}
@@ -1,8 +1,18 @@
+use std::{
+    fmt,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
 use sea_orm::{
-    sea_query::{ColumnDef, Index, Table},
-    ConnectionTrait, DbErr, DeriveIden, ExecResult,
+    sea_query::{
+        Alias, Asterisk, ColumnDef, Expr, Index, IndexCreateStatement, Query, Table,
+        TableAlterStatement, TableCreateStatement, TableDropStatement,
+    },
+    ConnectionTrait, DatabaseBackend, DbErr, DeriveIden, ExecResult, Statement,
 };
 
+use crate::{lock, SchemaOptions};
+
 #[derive(DeriveIden)]
 enum CasbinRule {
     Table,
@@ -14,10 +24,197 @@ enum CasbinRule {
     V3,
     V4,
     V5,
+    ExpiresAt,
+    DeletedAt,
+    Arity,
+    RuleHash,
+}
+
+#[derive(DeriveIden)]
+enum CasbinModel {
+    Table,
+    Id,
+    Name,
+    Version,
+    Text,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum CasbinRevision {
+    Table,
+    Name,
+    Revision,
+}
+
+#[derive(DeriveIden)]
+enum CasbinSnapshot {
+    Table,
+    Id,
+    Name,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum CasbinSnapshotRule {
+    Table,
+    Id,
+    SnapshotId,
+    Ptype,
+    V0,
+    V1,
+    V2,
+    V3,
+    V4,
+    V5,
+    ExpiresAt,
+    Arity,
+}
+
+// Columns added after `0.4.0`, they must be nullable so that they can be appended to existing tables.
+fn added_columns() -> Vec<ColumnDef> {
+    vec![
+        ColumnDef::new(CasbinRule::ExpiresAt)
+            .big_integer()
+            .null()
+            .to_owned(),
+        ColumnDef::new(CasbinRule::DeletedAt)
+            .big_integer()
+            .null()
+            .to_owned(),
+        ColumnDef::new(CasbinRule::Arity)
+            .integer()
+            .null()
+            .to_owned(),
+        // hex SHA-256, only set in tables created with `SchemaOptions::hashed_values`
+        ColumnDef::new(CasbinRule::RuleHash)
+            .char_len(64)
+            .null()
+            .to_owned(),
+    ]
+}
+
+// the collation is written into the DDL, so only plain names are accepted
+pub(crate) fn mysql_collation(
+    backend: DatabaseBackend,
+    options: &SchemaOptions,
+) -> Result<Option<&str>, DbErr> {
+    match &options.mysql_collation {
+        Some(collation) if backend == DatabaseBackend::MySql => {
+            if collation.is_empty()
+                || !collation
+                    .chars()
+                    .all(|x| x.is_ascii_alphanumeric() || x == '_')
+            {
+                return Err(DbErr::Custom(format!("invalid collation `{collation}`")));
+            }
+
+            Ok(Some(collation))
+        }
+        _ => Ok(None),
+    }
+}
+
+fn value_column(column: CasbinRule, options: &SchemaOptions, collation: Option<&str>) -> ColumnDef {
+    let mut column = ColumnDef::new(column);
+
+    if options.hashed_values {
+        column.text();
+    } else {
+        column.string_len(125);
+    }
+
+    if options.nullable_values {
+        column.null();
+    } else {
+        column.not_null();
+    }
+
+    if let Some(collation) = collation {
+        column.extra(format!("COLLATE {collation}"));
+    }
+
+    column
 }
 
 pub async fn up<C: ConnectionTrait>(conn: &C) -> Result<ExecResult, DbErr> {
-    let create_table = Table::create()
+    up_with_options(conn, &SchemaOptions::default()).await
+}
+
+/// Like `up`, but missing tables are created with `options`.
+pub async fn up_with_options<C: ConnectionTrait>(
+    conn: &C,
+    options: &SchemaOptions,
+) -> Result<ExecResult, DbErr> {
+    let builder = conn.get_database_backend();
+    let create_table = create_rule_table(builder, options)?;
+    let result = conn.execute(builder.build(&create_table)).await?;
+
+    upgrade(conn).await?;
+    up_snapshot(conn).await?;
+    conn.execute(builder.build(&create_revision_table()))
+        .await?;
+
+    if options.model_table {
+        conn.execute(builder.build(&create_model_table())).await?;
+    }
+
+    Ok(result)
+}
+
+/// The statements `up_with_options` runs on an empty database, in order.
+///
+/// On an existing database, `up` creates the index of `casbin_snapshot_rule` only if it's missing,
+/// and runs the statements of `upgrade_sql` for the missing columns of `casbin_rule`.
+pub fn up_sql(backend: DatabaseBackend, options: &SchemaOptions) -> Result<Vec<String>, DbErr> {
+    let [create_snapshot, create_snapshot_rule] = create_snapshot_tables();
+
+    let mut sql = vec![
+        backend
+            .build(&create_rule_table(backend, options)?)
+            .to_string(),
+        backend.build(&create_snapshot).to_string(),
+        backend.build(&create_snapshot_rule).to_string(),
+        backend.build(&create_snapshot_index()).to_string(),
+        backend.build(&create_revision_table()).to_string(),
+    ];
+
+    if options.model_table {
+        sql.push(backend.build(&create_model_table()).to_string());
+    }
+
+    Ok(sql)
+}
+
+/// The statements adding the columns introduced after `0.4.0` to `casbin_rule`, one per column.
+pub fn upgrade_sql(backend: DatabaseBackend) -> Vec<String> {
+    added_columns()
+        .into_iter()
+        .map(|column| backend.build(&add_column(column)).to_string())
+        .collect()
+}
+
+/// The statements `down` runs, in order.
+pub fn down_sql(backend: DatabaseBackend) -> Vec<String> {
+    drop_tables(backend)
+        .iter()
+        .map(|drop_table| backend.build(drop_table).to_string())
+        .collect()
+}
+
+pub(crate) fn create_rule_table(
+    backend: DatabaseBackend,
+    options: &SchemaOptions,
+) -> Result<TableCreateStatement, DbErr> {
+    let collation = mysql_collation(backend, options)?;
+
+    let mut ptype = ColumnDef::new(CasbinRule::Ptype);
+    ptype.string_len(18).not_null();
+    if let Some(collation) = collation {
+        ptype.extra(format!("COLLATE {collation}"));
+    }
+
+    let mut create_table = Table::create()
         .if_not_exists()
         .table(CasbinRule::Table)
         .col(
@@ -29,38 +226,552 @@ pub async fn up<C: ConnectionTrait>(conn: &C) -> Result<ExecResult, DbErr> {
         )
         // MySQL max key length is `3072` bytes, in `utf8mb4` charset, it's `3072 / 4 = 768` characters
         // 18 + 125 * 6 = 768
-        .col(ColumnDef::new(CasbinRule::Ptype).string_len(18).not_null())
-        .col(ColumnDef::new(CasbinRule::V0).string_len(125).not_null())
-        .col(ColumnDef::new(CasbinRule::V1).string_len(125).not_null())
-        .col(ColumnDef::new(CasbinRule::V2).string_len(125).not_null())
-        .col(ColumnDef::new(CasbinRule::V3).string_len(125).not_null())
-        .col(ColumnDef::new(CasbinRule::V4).string_len(125).not_null())
-        .col(ColumnDef::new(CasbinRule::V5).string_len(125).not_null())
+        .col(ptype)
+        .col(value_column(CasbinRule::V0, options, collation))
+        .col(value_column(CasbinRule::V1, options, collation))
+        .col(value_column(CasbinRule::V2, options, collation))
+        .col(value_column(CasbinRule::V3, options, collation))
+        .col(value_column(CasbinRule::V4, options, collation))
+        .col(value_column(CasbinRule::V5, options, collation))
+        .to_owned();
+
+    for column in added_columns() {
+        create_table.col(column);
+    }
+
+    let mut unique_key = Index::create().unique().table(CasbinRule::Table).to_owned();
+
+    if options.hashed_values {
+        unique_key
+            .name("unique_key_sea_orm_adapter_hash")
+            .col(CasbinRule::RuleHash);
+    } else {
+        unique_key
+            .name("unique_key_sea_orm_adapter")
+            .col(CasbinRule::Ptype)
+            .col(CasbinRule::V0)
+            .col(CasbinRule::V1)
+            .col(CasbinRule::V2)
+            .col(CasbinRule::V3)
+            .col(CasbinRule::V4)
+            .col(CasbinRule::V5);
+    }
+
+    create_table.index(&mut unique_key);
+
+    Ok(create_table)
+}
+
+fn create_model_table() -> TableCreateStatement {
+    Table::create()
+        .if_not_exists()
+        .table(CasbinModel::Table)
+        .col(
+            ColumnDef::new(CasbinModel::Id)
+                .big_integer()
+                .not_null()
+                .auto_increment()
+                .primary_key(),
+        )
+        .col(ColumnDef::new(CasbinModel::Name).string_len(125).not_null())
+        .col(
+            ColumnDef::new(CasbinModel::Version)
+                .big_integer()
+                .not_null(),
+        )
+        .col(ColumnDef::new(CasbinModel::Text).text().not_null())
+        .col(
+            ColumnDef::new(CasbinModel::CreatedAt)
+                .big_integer()
+                .not_null(),
+        )
         .index(
             Index::create()
-                .name("unique_key_sea_orm_adapter")
+                .name("unique_key_casbin_model")
                 .unique()
-                .table(CasbinRule::Table)
-                .col(CasbinRule::Ptype)
-                .col(CasbinRule::V0)
-                .col(CasbinRule::V1)
-                .col(CasbinRule::V2)
-                .col(CasbinRule::V3)
-                .col(CasbinRule::V4)
-                .col(CasbinRule::V5),
+                .table(CasbinModel::Table)
+                .col(CasbinModel::Name)
+                .col(CasbinModel::Version),
+        )
+        .to_owned()
+}
+
+// the row is inserted by the first `save_policy`
+fn create_revision_table() -> TableCreateStatement {
+    Table::create()
+        .if_not_exists()
+        .table(CasbinRevision::Table)
+        .col(
+            ColumnDef::new(CasbinRevision::Name)
+                .string_len(64)
+                .not_null()
+                .primary_key(),
+        )
+        .col(
+            ColumnDef::new(CasbinRevision::Revision)
+                .big_integer()
+                .not_null(),
+        )
+        .to_owned()
+}
+
+fn create_snapshot_tables() -> [TableCreateStatement; 2] {
+    let create_snapshot = Table::create()
+        .if_not_exists()
+        .table(CasbinSnapshot::Table)
+        .col(
+            ColumnDef::new(CasbinSnapshot::Id)
+                .big_integer()
+                .not_null()
+                .auto_increment()
+                .primary_key(),
+        )
+        .col(
+            ColumnDef::new(CasbinSnapshot::Name)
+                .string_len(125)
+                .not_null()
+                .unique_key(),
+        )
+        .col(
+            ColumnDef::new(CasbinSnapshot::CreatedAt)
+                .big_integer()
+                .not_null(),
         )
         .to_owned();
 
+    // values are copied from `casbin_rule` as they are, so the value columns are nullable `TEXT`
+    // to accept the rules of every schema
+    let create_snapshot_rule = Table::create()
+        .if_not_exists()
+        .table(CasbinSnapshotRule::Table)
+        .col(
+            ColumnDef::new(CasbinSnapshotRule::Id)
+                .big_integer()
+                .not_null()
+                .auto_increment()
+                .primary_key(),
+        )
+        .col(
+            ColumnDef::new(CasbinSnapshotRule::SnapshotId)
+                .big_integer()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(CasbinSnapshotRule::Ptype)
+                .string_len(18)
+                .not_null(),
+        )
+        .col(ColumnDef::new(CasbinSnapshotRule::V0).text().null())
+        .col(ColumnDef::new(CasbinSnapshotRule::V1).text().null())
+        .col(ColumnDef::new(CasbinSnapshotRule::V2).text().null())
+        .col(ColumnDef::new(CasbinSnapshotRule::V3).text().null())
+        .col(ColumnDef::new(CasbinSnapshotRule::V4).text().null())
+        .col(ColumnDef::new(CasbinSnapshotRule::V5).text().null())
+        .col(
+            ColumnDef::new(CasbinSnapshotRule::ExpiresAt)
+                .big_integer()
+                .null(),
+        )
+        .col(ColumnDef::new(CasbinSnapshotRule::Arity).integer().null())
+        .to_owned();
+
+    [create_snapshot, create_snapshot_rule]
+}
+
+fn create_snapshot_index() -> IndexCreateStatement {
+    Index::create()
+        .name("idx_casbin_snapshot_rule")
+        .table(CasbinSnapshotRule::Table)
+        .col(CasbinSnapshotRule::SnapshotId)
+        .to_owned()
+}
+
+async fn up_snapshot<C: ConnectionTrait>(conn: &C) -> Result<(), DbErr> {
     let builder = conn.get_database_backend();
-    conn.execute(builder.build(&create_table)).await
+
+    for create_table in create_snapshot_tables() {
+        conn.execute(builder.build(&create_table)).await?;
+    }
+
+    // MySQL does not support `CREATE INDEX IF NOT EXISTS`
+    if !has_index(conn, "casbin_snapshot_rule", "idx_casbin_snapshot_rule").await? {
+        conn.execute(builder.build(&create_snapshot_index()))
+            .await?;
+    }
+
+    Ok(())
 }
 
-pub async fn down<C: ConnectionTrait>(conn: &C) -> Result<ExecResult, DbErr> {
-    let drop_table = Table::drop()
-        .if_exists()
+fn add_column(column: ColumnDef) -> TableAlterStatement {
+    Table::alter()
         .table(CasbinRule::Table)
-        .to_owned();
+        .add_column(column)
+        .to_owned()
+}
 
+/// Appends the columns missing from a table created by an older version.
+async fn upgrade<C: ConnectionTrait>(conn: &C) -> Result<(), DbErr> {
     let builder = conn.get_database_backend();
+
+    for column in added_columns() {
+        if has_column(conn, "casbin_rule", &column.get_column_name()).await? {
+            continue;
+        }
+
+        conn.execute(builder.build(&add_column(column))).await?;
+    }
+
+    Ok(())
+}
+
+async fn has_column<C: ConnectionTrait>(
+    conn: &C,
+    table: &str,
+    column: &str,
+) -> Result<bool, DbErr> {
+    let backend = conn.get_database_backend();
+
+    let sql = match backend {
+        DatabaseBackend::MySql => {
+            "SELECT 1 FROM information_schema.columns \
+             WHERE table_schema = DATABASE() AND table_name = ? AND column_name = ?"
+        }
+        DatabaseBackend::Postgres => {
+            "SELECT 1 FROM information_schema.columns \
+             WHERE table_schema = current_schema() AND table_name = $1 AND column_name = $2"
+        }
+        DatabaseBackend::Sqlite => "SELECT 1 FROM pragma_table_info(?) WHERE name = ?",
+    };
+
+    let stmt = Statement::from_sql_and_values(backend, sql, [table.into(), column.into()]);
+
+    conn.query_one(stmt).await.map(|row| row.is_some())
+}
+
+pub(crate) async fn has_index<C: ConnectionTrait>(
+    conn: &C,
+    table: &str,
+    index: &str,
+) -> Result<bool, DbErr> {
+    let backend = conn.get_database_backend();
+
+    let sql = match backend {
+        DatabaseBackend::MySql => {
+            "SELECT 1 FROM information_schema.statistics \
+             WHERE table_schema = DATABASE() AND table_name = ? AND index_name = ?"
+        }
+        DatabaseBackend::Postgres => {
+            "SELECT 1 FROM pg_indexes \
+             WHERE schemaname = current_schema() AND tablename = $1 AND indexname = $2"
+        }
+        DatabaseBackend::Sqlite => "SELECT 1 FROM pragma_index_list(?) WHERE name = ?",
+    };
+
+    let stmt = Statement::from_sql_and_values(backend, sql, [table.into(), index.into()]);
+
+    conn.query_one(stmt).await.map(|row| row.is_some())
+}
+
+// in the order `down` drops them, `casbin_model` is dropped even if `up` didn't create it
+const TABLES: [&str; 6] = [
+    lock::LOCK_TABLE,
+    "casbin_snapshot",
+    "casbin_snapshot_rule",
+    "casbin_model",
+    "casbin_revision",
+    "casbin_rule",
+];
+
+// index names are unique per schema on Postgres and SQLite, a backup can't keep them
+const NAMED_INDEXES: [(&str, &str); 4] = [
+    ("casbin_rule", "unique_key_sea_orm_adapter"),
+    ("casbin_rule", "unique_key_sea_orm_adapter_hash"),
+    ("casbin_snapshot_rule", "idx_casbin_snapshot_rule"),
+    ("casbin_model", "unique_key_casbin_model"),
+];
+
+// the lock table only exists on SQLite, the other backends have database locks
+fn backend_tables(backend: DatabaseBackend) -> impl Iterator<Item = &'static str> {
+    TABLES
+        .into_iter()
+        .filter(move |table| *table != lock::LOCK_TABLE || backend == DatabaseBackend::Sqlite)
+}
+
+fn drop_tables(backend: DatabaseBackend) -> Vec<TableDropStatement> {
+    // SQLite can only drop one table per statement
+    backend_tables(backend)
+        .map(|table| {
+            Table::drop()
+                .if_exists()
+                .table(Alias::new(table))
+                .to_owned()
+        })
+        .collect()
+}
+
+/// Drops every table unconditionally, see `down_guarded` to keep the rules.
+pub async fn down<C: ConnectionTrait>(conn: &C) -> Result<ExecResult, DbErr> {
+    let builder = conn.get_database_backend();
+    let mut drop_tables = drop_tables(builder);
+    let drop_table = drop_tables.pop().expect("`casbin_rule` is dropped last");
+
+    for drop_table in drop_tables {
+        conn.execute(builder.build(&drop_table)).await?;
+    }
+
     conn.execute(builder.build(&drop_table)).await
 }
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DownMode {
+    /// Drops the tables, nothing is changed if one of them still has rows.
+    #[default]
+    Guarded,
+    /// Drops the tables even if they have rows, like `down`.
+    Force,
+    /// Renames the tables to `<table>_backup_<unix time>` instead of dropping them.
+    Backup,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DownReport {
+    /// Tables that were dropped.
+    pub dropped: Vec<String>,
+    /// Tables that were renamed, with their new name.
+    pub renamed: Vec<(String, String)>,
+}
+
+/// Returned by `down_guarded`, with the tables dropped or renamed before `source` happened.
+#[derive(Debug)]
+pub struct DownError {
+    pub report: DownReport,
+    pub source: DbErr,
+}
+
+impl fmt::Display for DownError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.source, f)?;
+
+        for (index, table) in self.report.dropped.iter().enumerate() {
+            let separator = if index == 0 {
+                ", after dropping "
+            } else {
+                ", "
+            };
+            write!(f, "{separator}`{table}`")?;
+        }
+
+        for (index, (table, backup)) in self.report.renamed.iter().enumerate() {
+            let separator = if index == 0 {
+                ", after renaming "
+            } else {
+                ", "
+            };
+            write!(f, "{separator}`{table}` to `{backup}`")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for DownError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+/// Like `down`, but refuses to drop a table that still has rows unless `mode` is `DownMode::Force`,
+/// or keeps the tables under another name with `DownMode::Backup`.
+///
+/// The tables are checked before anything is changed. Explicitly named indexes of a backup are
+/// renamed with the same suffix, SQLite can't rename them so they are dropped.
+///
+/// The tables are dropped or renamed one at a time, outside of a transaction since MySQL commits
+/// DDL statements anyway. If one of them fails, the tables already handled stay dropped or renamed
+/// and are listed in the `report` of the `DownError`, the backups can be renamed back by hand.
+pub async fn down_guarded<C: ConnectionTrait>(
+    conn: &C,
+    mode: DownMode,
+) -> Result<DownReport, DownError> {
+    let mut report = DownReport::default();
+
+    match apply_down(conn, mode, &mut report).await {
+        Ok(()) => Ok(report),
+        Err(source) => Err(DownError { report, source }),
+    }
+}
+
+async fn apply_down<C: ConnectionTrait>(
+    conn: &C,
+    mode: DownMode,
+    report: &mut DownReport,
+) -> Result<(), DbErr> {
+    let builder = conn.get_database_backend();
+    let mut tables = Vec::with_capacity(TABLES.len());
+
+    for table in backend_tables(builder) {
+        if !has_table(conn, table).await? {
+            continue;
+        }
+
+        // the revision only counts the saves of the rules, and the lock has a row once migrated
+        if mode == DownMode::Guarded && table != "casbin_revision" && table != lock::LOCK_TABLE {
+            let rows = count_rows(conn, table).await?;
+            if rows > 0 {
+                return Err(DbErr::Custom(format!(
+                    "`{table}` has {rows} rows, use `DownMode::Force` to drop it \
+                     or `DownMode::Backup` to keep it"
+                )));
+            }
+        }
+
+        tables.push(table);
+    }
+
+    if mode != DownMode::Backup {
+        for table in tables {
+            let drop_table = Table::drop().table(Alias::new(table)).to_owned();
+            conn.execute(builder.build(&drop_table)).await?;
+            report.dropped.push(table.to_owned());
+        }
+
+        return Ok(());
+    }
+
+    let suffix = format!(
+        "_backup_{}",
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs())
+    );
+
+    for table in tables {
+        let backup = format!("{table}{suffix}");
+        let rename_table = Table::rename()
+            .table(Alias::new(table), Alias::new(&backup))
+            .to_owned();
+        conn.execute(builder.build(&rename_table)).await?;
+
+        for (_, index) in NAMED_INDEXES.iter().filter(|(name, _)| *name == table) {
+            if !has_index(conn, &backup, index).await? {
+                continue;
+            }
+
+            let sql = match builder {
+                // MySQL index names are unique per table
+                DatabaseBackend::MySql => continue,
+                DatabaseBackend::Postgres => format!(
+                    "ALTER INDEX {} RENAME TO {}",
+                    quote(builder, index),
+                    quote(builder, &format!("{index}{suffix}"))
+                ),
+                DatabaseBackend::Sqlite => format!("DROP INDEX {}", quote(builder, index)),
+            };
+
+            conn.execute(Statement::from_string(builder, sql)).await?;
+        }
+
+        report.renamed.push((table.to_owned(), backup));
+    }
+
+    Ok(())
+}
+
+async fn has_table<C: ConnectionTrait>(conn: &C, table: &str) -> Result<bool, DbErr> {
+    let backend = conn.get_database_backend();
+
+    let sql = match backend {
+        DatabaseBackend::MySql => {
+            "SELECT 1 FROM information_schema.tables \
+             WHERE table_schema = DATABASE() AND table_name = ?"
+        }
+        DatabaseBackend::Postgres => {
+            "SELECT 1 FROM information_schema.tables \
+             WHERE table_schema = current_schema() AND table_name = $1"
+        }
+        DatabaseBackend::Sqlite => "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?",
+    };
+
+    let stmt = Statement::from_sql_and_values(backend, sql, [table.into()]);
+
+    conn.query_one(stmt).await.map(|row| row.is_some())
+}
+
+pub(crate) async fn count_rows<C: ConnectionTrait>(conn: &C, table: &str) -> Result<i64, DbErr> {
+    let backend = conn.get_database_backend();
+    let select = Query::select()
+        .expr(Expr::col(Asterisk).count())
+        .from(Alias::new(table))
+        .to_owned();
+
+    match conn.query_one(backend.build(&select)).await? {
+        Some(row) => row.try_get_by_index(0),
+        None => Ok(0),
+    }
+}
+
+pub(crate) fn quote(backend: DatabaseBackend, name: &str) -> String {
+    let quote = match backend {
+        DatabaseBackend::MySql => "`",
+        DatabaseBackend::Postgres | DatabaseBackend::Sqlite => "\"",
+    };
+
+    format!(
+        "{quote}{}{quote}",
+        name.replace(quote, &format!("{quote}{quote}"))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use sea_orm::sea_query::{MysqlQueryBuilder, PostgresQueryBuilder};
+
+    use super::*;
+
+    #[test]
+    fn test_collation() {
+        let mut options = SchemaOptions::new();
+        options.mysql_collation("utf8mb4_bin");
+
+        let sql = create_rule_table(DatabaseBackend::MySql, &options)
+            .unwrap()
+            .to_string(MysqlQueryBuilder);
+        assert!(sql.contains("`ptype` varchar(18) NOT NULL COLLATE utf8mb4_bin"));
+        assert!(sql.contains("`v5` varchar(125) NOT NULL COLLATE utf8mb4_bin"));
+
+        let sql = create_rule_table(DatabaseBackend::Postgres, &options)
+            .unwrap()
+            .to_string(PostgresQueryBuilder);
+        assert!(!sql.contains("COLLATE"));
+
+        options.mysql_collation("utf8mb4_bin; DROP TABLE casbin_rule");
+        assert!(create_rule_table(DatabaseBackend::MySql, &options).is_err());
+    }
+
+    #[test]
+    fn test_preview() {
+        let mut options = SchemaOptions::new();
+        let sql = up_sql(DatabaseBackend::Postgres, &options).unwrap();
+        assert_eq!(sql.len(), 5);
+        assert!(sql[0].starts_with(r#"CREATE TABLE IF NOT EXISTS "casbin_rule""#));
+        assert!(sql[3].starts_with(r#"CREATE INDEX "idx_casbin_snapshot_rule""#));
+
+        options.model_table(true);
+        let sql = up_sql(DatabaseBackend::Postgres, &options).unwrap();
+        assert_eq!(sql.len(), 6);
+        assert!(sql[5].starts_with(r#"CREATE TABLE IF NOT EXISTS "casbin_model""#));
+
+        let sql = upgrade_sql(DatabaseBackend::MySql);
+        assert_eq!(sql.len(), added_columns().len());
+        assert_eq!(
+            sql[0],
+            "ALTER TABLE `casbin_rule` ADD COLUMN `expires_at` bigint NULL"
+        );
+
+        let sql = down_sql(DatabaseBackend::Sqlite);
+        assert_eq!(sql.len(), 6);
+        assert_eq!(sql[0], r#"DROP TABLE IF EXISTS "casbin_migration_lock""#);
+        assert_eq!(sql[5], r#"DROP TABLE IF EXISTS "casbin_rule""#);
+        assert_eq!(down_sql(DatabaseBackend::Postgres).len(), 5);
+    }
+}
@@ -0,0 +1,103 @@
+use std::time::SystemTime;
+
+use casbin::{error::AdapterError, Error as CasbinError, Result};
+use sea_orm::{
+    ActiveModelTrait,
+    ActiveValue::{NotSet, Set},
+    ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, QueryOrder, QuerySelect,
+};
+
+use crate::{
+    action,
+    entity::model::{self, Column, Entity},
+    telemetry, Error,
+};
+
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(backend = ?conn.get_database_backend(), name = name, version = version, rows_affected = tracing::field::Empty)
+    )
+)]
+pub(crate) async fn save_model<C: ConnectionTrait>(
+    conn: &C,
+    name: &str,
+    version: i64,
+    text: &str,
+) -> Result<()> {
+    let model = model::ActiveModel {
+        id: NotSet,
+        name: Set(name.to_owned()),
+        version: Set(version),
+        text: Set(text.to_owned()),
+        created_at: Set(action::unix_timestamp(SystemTime::now())),
+    };
+
+    model
+        .insert(conn)
+        .await
+        .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))?;
+    telemetry::record("rows_affected", 1);
+
+    Ok(())
+}
+
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(backend = ?conn.get_database_backend(), name = name, version = ?version)
+    )
+)]
+pub(crate) async fn load_model<C: ConnectionTrait>(
+    conn: &C,
+    name: &str,
+    version: Option<i64>,
+) -> Result<model::Model> {
+    let mut query = Entity::find().filter(Column::Name.eq(name));
+
+    query = match version {
+        Some(version) => query.filter(Column::Version.eq(version)),
+        None => query.order_by_desc(Column::Version).limit(1),
+    };
+
+    query
+        .one(conn)
+        .await
+        .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))?
+        .ok_or_else(|| {
+            CasbinError::from(AdapterError(Box::new(Error::ModelNotFound {
+                name: name.to_owned(),
+                version,
+            })))
+        })
+}
+
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(backend = ?conn.get_database_backend(), name = name, versions = tracing::field::Empty)
+    )
+)]
+pub(crate) async fn list_model_versions<C: ConnectionTrait>(
+    conn: &C,
+    name: &str,
+) -> Result<Vec<i64>> {
+    let versions: Vec<i64> = Entity::find()
+        .select_only()
+        .column(Column::Version)
+        .filter(Column::Name.eq(name))
+        .order_by_asc(Column::Version)
+        .into_tuple()
+        .all(conn)
+        .await
+        .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))?;
+    telemetry::record("versions", versions.len() as u64);
+
+    Ok(versions)
+}
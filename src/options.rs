@@ -0,0 +1,149 @@
+use std::{collections::HashMap, time::Duration};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ImportMode {
+    /// Keeps the stored rules and adds the missing ones.
+    #[default]
+    Merge,
+    /// Removes the stored rules first.
+    Replace,
+}
+
+/// Shape of the tables created by [`up_with_options`](crate::up_with_options), existing tables are not altered.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SchemaOptions {
+    pub(crate) nullable_values: bool,
+    pub(crate) hashed_values: bool,
+    pub(crate) mysql_collation: Option<String>,
+    pub(crate) model_table: bool,
+}
+
+impl SchemaOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates `v0` to `v5` as nullable columns and stores the unused positions after the last
+    /// non-empty value as `NULL` instead of `""`.
+    ///
+    /// The unique index doesn't compare `NULL` values, so duplicates are rejected by the adapter,
+    /// writers that bypass it can store the same rule twice.
+    pub fn nullable_values(&mut self, value: bool) -> &mut Self {
+        self.nullable_values = value;
+        self
+    }
+
+    /// Creates `v0` to `v5` as `TEXT` columns without a length limit, and makes rules unique by a
+    /// SHA-256 hash of the ptype and values stored in `rule_hash`.
+    ///
+    /// The `varchar(125)` limit only exists so that the unique index over all columns fits in
+    /// MySQL's 3072-byte key limit, the index on the hash doesn't need it.
+    pub fn hashed_values(&mut self, value: bool) -> &mut Self {
+        self.hashed_values = value;
+        self
+    }
+
+    /// Creates `ptype` and `v0` to `v5` with this collation on MySQL, e.g. `utf8mb4_bin`, and makes
+    /// `SeaOrmAdapter::new_with_options` fail if an existing table uses another one, even without
+    /// `AdapterOptions::verify_schema`.
+    ///
+    /// The default `utf8mb4_0900_ai_ci` compares `Alice` and `alice` as equal, unlike casbin. Other
+    /// backends compare values case-sensitively and ignore this option.
+    pub fn mysql_collation(&mut self, collation: impl Into<String>) -> &mut Self {
+        self.mysql_collation = Some(collation.into());
+        self
+    }
+
+    /// Creates the `casbin_model` table of `SeaOrmAdapter::save_model`, the other tables don't
+    /// depend on it.
+    pub fn model_table(&mut self, value: bool) -> &mut Self {
+        self.model_table = value;
+        self
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct AdapterOptions {
+    pub(crate) schema: SchemaOptions,
+    pub(crate) verify_schema: bool,
+    pub(crate) migration_lock_timeout: Option<Duration>,
+    pub(crate) soft_delete: bool,
+    pub(crate) strict_load: bool,
+    pub(crate) preserve_arity: bool,
+    pub(crate) sections: HashMap<String, String>,
+    #[cfg(feature = "tracing")]
+    pub(crate) redact_values: bool,
+}
+
+impl AdapterOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates missing tables with `schema`, and stores rules the way this schema expects.
+    pub fn schema(&mut self, schema: SchemaOptions) -> &mut Self {
+        self.schema = schema;
+        self
+    }
+
+    /// Makes `SeaOrmAdapter::new_with_options` fail with [`Error::SchemaMismatch`](crate::Error::SchemaMismatch)
+    /// if an existing table differs from the one `schema` describes.
+    pub fn verify_schema(&mut self, value: bool) -> &mut Self {
+        self.verify_schema = value;
+        self
+    }
+
+    /// How long `SeaOrmAdapter::new_with_options` waits for another instance to finish migrating,
+    /// before failing with [`Error::MigrationLockTimeout`](crate::Error::MigrationLockTimeout),
+    /// 60 seconds by default.
+    pub fn migration_lock_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.migration_lock_timeout = Some(timeout);
+        self
+    }
+
+    /// Removals set the `deleted_at` column instead of deleting rows, and adding a removed rule revives its row.
+    pub fn soft_delete(&mut self, value: bool) -> &mut Self {
+        self.soft_delete = value;
+        self
+    }
+
+    /// Loads fail with [`Error::RulesSkipped`](crate::Error::RulesSkipped) instead of skipping rules the model can't accept.
+    pub fn strict_load(&mut self, value: bool) -> &mut Self {
+        self.strict_load = value;
+        self
+    }
+
+    /// Records the number of values of added rules, so that trailing empty values are loaded back.
+    ///
+    /// Rules that only differ by trailing empty values are still stored in the same row.
+    pub fn preserve_arity(&mut self, value: bool) -> &mut Self {
+        self.preserve_arity = value;
+        self
+    }
+
+    /// Loads rules of `ptype` into `sec`, by default the section is the first character of the ptype.
+    pub fn map_section(&mut self, ptype: impl Into<String>, sec: impl Into<String>) -> &mut Self {
+        self.sections.insert(ptype.into(), sec.into());
+        self
+    }
+
+    /// Leaves the rule values out of the spans of the `Adapter` methods, and the messages out of
+    /// their error events, the ptype, counts and error kinds are still recorded.
+    #[cfg(feature = "tracing")]
+    pub fn redact_values(&mut self, value: bool) -> &mut Self {
+        self.redact_values = value;
+        self
+    }
+
+    pub(crate) fn migration_lock_timeout_or_default(&self) -> Duration {
+        self.migration_lock_timeout
+            .unwrap_or(Duration::from_secs(60))
+    }
+
+    pub(crate) fn section_of(&self, ptype: &str) -> Option<String> {
+        match self.sections.get(ptype) {
+            Some(sec) => Some(sec.clone()),
+            None => ptype.chars().next().map(|x| x.to_string()),
+        }
+    }
+}
@@ -0,0 +1,269 @@
+use std::{fmt, str::FromStr, time::SystemTime};
+
+use casbin::{error::AdapterError, Error as CasbinError, Result};
+use sea_orm::{
+    sea_query::{Expr, Func, SimpleExpr},
+    ColumnTrait, Condition, ConnectionTrait, EntityTrait, Order, QueryFilter, QueryOrder,
+    QuerySelect, Value,
+};
+
+use crate::{
+    action::{self, VALUE_COLUMNS},
+    entity::{self, Column, Entity},
+    Error, PolicyRule,
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SortKey {
+    /// Insertion order.
+    #[default]
+    Id,
+    Ptype,
+    /// The value at this position, from 0 to 5.
+    Field(usize),
+}
+
+impl SortKey {
+    fn column(self) -> Column {
+        match self {
+            SortKey::Id => Column::Id,
+            SortKey::Ptype => Column::Ptype,
+            SortKey::Field(index) => VALUE_COLUMNS[index],
+        }
+    }
+
+    // backends don't agree on where `NULL` sorts and `NULL` fails every comparison, so unused
+    // positions are sorted and compared as empty values
+    fn expr(self, nullable: bool) -> SimpleExpr {
+        match self {
+            SortKey::Field(_) if nullable => {
+                Func::coalesce([Expr::col(self.column()).into(), Expr::val("").into()]).into()
+            }
+            _ => Expr::col(self.column()).into(),
+        }
+    }
+
+    fn value_of(self, model: &entity::Model) -> Option<String> {
+        let value = match self {
+            SortKey::Id => return None,
+            SortKey::Ptype => return Some(model.ptype.clone()),
+            SortKey::Field(0) => &model.v0,
+            SortKey::Field(1) => &model.v1,
+            SortKey::Field(2) => &model.v2,
+            SortKey::Field(3) => &model.v3,
+            SortKey::Field(4) => &model.v4,
+            SortKey::Field(_) => &model.v5,
+        };
+
+        Some(value.clone().unwrap_or_default())
+    }
+}
+
+/// Position after the last rule of a page, pass it to [`PolicyQuery::after`] to get the next page.
+///
+/// Its string form can be handed to clients and parsed back, it's only valid for a query with the same sort key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PolicyCursor {
+    id: i64,
+    value: Option<String>,
+}
+
+impl fmt::Display for PolicyCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.value {
+            Some(value) => write!(f, "{}:{}", self.id, value),
+            None => write!(f, "{}", self.id),
+        }
+    }
+}
+
+impl FromStr for PolicyCursor {
+    type Err = Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let (id, value) = match s.split_once(':') {
+            Some((id, value)) => (id, Some(value.to_owned())),
+            None => (s, None),
+        };
+
+        match id.parse() {
+            Ok(id) => Ok(PolicyCursor { id, value }),
+            Err(_) => Err(Error::InvalidCursor(s.to_owned())),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PolicyPage {
+    pub rules: Vec<PolicyRule>,
+    /// `None` on the last page, or when the query has no limit.
+    pub next: Option<PolicyCursor>,
+}
+
+/// Selects stored rules for [`SeaOrmAdapter::query_policies`](crate::SeaOrmAdapter::query_policies).
+///
+/// Expired and removed rules are never returned, ties in the sort key are ordered by insertion.
+#[derive(Clone, Debug, Default)]
+pub struct PolicyQuery {
+    ptype: Option<String>,
+    fields: [Option<String>; 6],
+    sort: SortKey,
+    descending: bool,
+    limit: Option<u64>,
+    offset: Option<u64>,
+    after: Option<PolicyCursor>,
+}
+
+impl PolicyQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn ptype(&mut self, ptype: impl Into<String>) -> &mut Self {
+        self.ptype = Some(ptype.into());
+        self
+    }
+
+    /// Only returns rules whose value at `index` is `value`, an empty `value` matches an empty field
+    /// or an unused position.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is greater than 5.
+    pub fn field(&mut self, index: usize, value: impl Into<String>) -> &mut Self {
+        assert!(index < 6, "a rule has at most 6 values");
+
+        self.fields[index] = Some(value.into());
+        self
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `key` is a field greater than 5.
+    pub fn order_by(&mut self, key: SortKey) -> &mut Self {
+        if let SortKey::Field(index) = key {
+            assert!(index < 6, "a rule has at most 6 values");
+        }
+
+        self.sort = key;
+        self
+    }
+
+    pub fn descending(&mut self, value: bool) -> &mut Self {
+        self.descending = value;
+        self
+    }
+
+    pub fn limit(&mut self, limit: u64) -> &mut Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn offset(&mut self, offset: u64) -> &mut Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    /// Starts after the last rule of a previous page, unlike an offset it's not shifted by concurrent changes.
+    pub fn after(&mut self, cursor: PolicyCursor) -> &mut Self {
+        self.after = Some(cursor);
+        self
+    }
+
+    fn cursor_condition(&self, cursor: &PolicyCursor, nullable: bool) -> Result<Condition> {
+        let after = |expr: SimpleExpr, value: Value| {
+            if self.descending {
+                Expr::expr(expr).lt(value)
+            } else {
+                Expr::expr(expr).gt(value)
+            }
+        };
+
+        if self.sort == SortKey::Id {
+            return Ok(Condition::all().add(after(Expr::col(Column::Id).into(), cursor.id.into())));
+        }
+
+        let Some(value) = &cursor.value else {
+            return Err(CasbinError::from(AdapterError(Box::new(
+                Error::InvalidCursor(cursor.to_string()),
+            ))));
+        };
+        let expr = self.sort.expr(nullable);
+
+        Ok(Condition::any().add(after(expr.clone(), value.into())).add(
+            Condition::all()
+                .add(Expr::expr(expr).eq(value))
+                .add(after(Expr::col(Column::Id).into(), cursor.id.into())),
+        ))
+    }
+}
+
+pub(crate) async fn query_policies<C: ConnectionTrait>(
+    conn: &C,
+    query: &PolicyQuery,
+    nullable: bool,
+) -> Result<(Vec<entity::Model>, Option<PolicyCursor>)> {
+    let mut conditions = Condition::all()
+        .add(action::is_active(action::unix_timestamp(SystemTime::now())))
+        .add_option(query.ptype.as_ref().map(|x| Column::Ptype.eq(x)));
+
+    for (column, value) in VALUE_COLUMNS.into_iter().zip(&query.fields) {
+        let Some(value) = value else {
+            continue;
+        };
+
+        conditions = if value.is_empty() && nullable {
+            conditions.add(Condition::any().add(column.eq(value)).add(column.is_null()))
+        } else {
+            conditions.add(column.eq(value))
+        };
+    }
+
+    if let Some(cursor) = &query.after {
+        conditions = conditions.add(query.cursor_condition(cursor, nullable)?);
+    }
+
+    let order = if query.descending {
+        Order::Desc
+    } else {
+        Order::Asc
+    };
+
+    let mut select = Entity::find().filter(conditions);
+
+    if query.sort != SortKey::Id {
+        select = select.order_by(query.sort.expr(nullable), order.clone());
+    }
+
+    let limit = match (query.limit, query.offset) {
+        // one more rule tells whether there is a next page
+        (Some(limit), _) => Some(limit + 1),
+        // MySQL and SQLite reject an `OFFSET` without a `LIMIT`, so the limit can't be reached
+        (None, Some(_)) => Some(i64::MAX as u64),
+        (None, None) => None,
+    };
+
+    select = select
+        .order_by(Column::Id, order)
+        .limit(limit)
+        .offset(query.offset);
+
+    let mut rules = select
+        .all(conn)
+        .await
+        .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))?;
+
+    let next = match query.limit {
+        Some(limit) if rules.len() as u64 > limit => {
+            rules.truncate(limit as usize);
+
+            rules.last().map(|x| PolicyCursor {
+                id: x.id,
+                value: query.sort.value_of(x),
+            })
+        }
+        _ => None,
+    };
+
+    Ok((rules, next))
+}
@@ -0,0 +1,58 @@
+use std::fmt;
+
+/// Rules read from the database but not loaded into the model.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LoadReport {
+    pub skipped: Vec<SkippedRule>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SkippedRule {
+    /// Primary key of the row in `casbin_rule`.
+    pub id: i64,
+    pub ptype: String,
+    pub reason: SkipReason,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SkipReason {
+    /// The model has no section for the ptype.
+    MissingSection(String),
+    /// The section exists, but the model does not define the ptype.
+    MissingPtype,
+    /// All values of the row are empty.
+    EmptyRule,
+}
+
+impl LoadReport {
+    pub fn is_empty(&self) -> bool {
+        self.skipped.is_empty()
+    }
+}
+
+impl fmt::Display for LoadReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} rule(s) skipped:", self.skipped.len())?;
+
+        for rule in &self.skipped {
+            write!(
+                f,
+                " [id {}, ptype `{}`: {}]",
+                rule.id, rule.ptype, rule.reason
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkipReason::MissingSection(sec) => write!(f, "section `{sec}` is not in the model"),
+            SkipReason::MissingPtype => f.write_str("ptype is not in the model"),
+            SkipReason::EmptyRule => f.write_str("all values are empty"),
+        }
+    }
+}
@@ -0,0 +1,339 @@
+use std::{fmt, str::FromStr};
+
+use sea_orm::ActiveValue::NotSet;
+
+use crate::{
+    action::{Rule, RuleWithType},
+    csv,
+    entity::{self, snapshot_rule},
+    Error,
+};
+
+/// A stored rule, `values` are `v0` to `v5` without the trailing empty ones, unless the row records its arity.
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(deny_unknown_fields)
+)]
+pub struct PolicyRule {
+    pub ptype: String,
+    pub values: Vec<String>,
+}
+
+impl PolicyRule {
+    pub fn new(ptype: impl Into<String>, values: Vec<String>) -> Self {
+        PolicyRule {
+            ptype: ptype.into(),
+            values,
+        }
+    }
+
+    // casbin stores shorter rules padded with empty values, the arity tells how many values are real
+    pub(crate) fn from_columns(ptype: &str, columns: [&str; 6], arity: Option<i32>) -> Self {
+        let mut values = columns.to_vec();
+
+        match arity {
+            Some(arity) => values.truncate(arity.clamp(0, 6) as usize),
+            None => {
+                while values.last().is_some_and(|x| x.is_empty()) {
+                    values.pop();
+                }
+            }
+        }
+
+        PolicyRule {
+            ptype: ptype.to_owned(),
+            values: values.into_iter().map(|x| x.to_owned()).collect(),
+        }
+    }
+
+    // the table has a `ptype` column and `v0` to `v5`
+    pub(crate) fn check(&self) -> Result<(), String> {
+        if self.ptype.is_empty() || self.values.is_empty() {
+            return Err("a rule needs a ptype and at least one value".to_owned());
+        }
+
+        if self.values.len() > 6 {
+            return Err(format!(
+                "a rule has at most 6 values, found {}",
+                self.values.len()
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl From<&entity::Model> for PolicyRule {
+    fn from(model: &entity::Model) -> Self {
+        PolicyRule::from_columns(
+            &model.ptype,
+            [
+                &model.v0, &model.v1, &model.v2, &model.v3, &model.v4, &model.v5,
+            ]
+            .map(|x| x.as_deref().unwrap_or_default()),
+            model.arity,
+        )
+    }
+}
+
+impl From<entity::Model> for PolicyRule {
+    fn from(model: entity::Model) -> Self {
+        PolicyRule::from(&model)
+    }
+}
+
+impl From<&snapshot_rule::Model> for PolicyRule {
+    fn from(model: &snapshot_rule::Model) -> Self {
+        PolicyRule::from_columns(
+            &model.ptype,
+            [
+                &model.v0, &model.v1, &model.v2, &model.v3, &model.v4, &model.v5,
+            ]
+            .map(|x| x.as_deref().unwrap_or_default()),
+            model.arity,
+        )
+    }
+}
+
+/// Unset and `NULL` values are empty, but the ptype must be set.
+impl TryFrom<entity::ActiveModel> for PolicyRule {
+    type Error = Error;
+
+    fn try_from(mut model: entity::ActiveModel) -> Result<Self, Self::Error> {
+        let Some(ptype) = model.ptype.take() else {
+            return Err(Error::InvalidRule {
+                line: 0,
+                reason: "the ptype is not set".to_owned(),
+            });
+        };
+
+        let values = [
+            model.v0.take(),
+            model.v1.take(),
+            model.v2.take(),
+            model.v3.take(),
+            model.v4.take(),
+            model.v5.take(),
+        ]
+        .map(|x| x.flatten().unwrap_or_default());
+
+        Ok(PolicyRule::from_columns(
+            &ptype,
+            values.each_ref().map(String::as_str),
+            model.arity.take().flatten(),
+        ))
+    }
+}
+
+/// A row of the default schema, or of the one with `SchemaOptions::hashed_values`, with an `id` of
+/// 0 and the values padded to 6 columns.
+///
+/// The arity is recorded like with `AdapterOptions::preserve_arity`, so the row converts back to
+/// the same rule, trailing empty values included.
+impl TryFrom<&PolicyRule> for entity::Model {
+    type Error = Error;
+
+    fn try_from(rule: &PolicyRule) -> Result<Self, Self::Error> {
+        rule.check()
+            .map_err(|reason| Error::InvalidRule { line: 0, reason })?;
+
+        let mut rule_with_type =
+            RuleWithType::from_rule(&rule.ptype, Rule::from_string(&rule.values));
+        rule_with_type.arity = Some(rule.values.len() as i32);
+        rule_with_type.hashed = true;
+
+        let [v0, v1, v2, v3, v4, v5] = rule_with_type.columns().map(|x| x.map(str::to_owned));
+
+        Ok(entity::Model {
+            id: 0,
+            ptype: rule.ptype.clone(),
+            v0,
+            v1,
+            v2,
+            v3,
+            v4,
+            v5,
+            expires_at: None,
+            deleted_at: None,
+            arity: rule_with_type.arity,
+            rule_hash: Some(rule_with_type.hash()),
+        })
+    }
+}
+
+impl TryFrom<PolicyRule> for entity::Model {
+    type Error = Error;
+
+    fn try_from(rule: PolicyRule) -> Result<Self, Self::Error> {
+        entity::Model::try_from(&rule)
+    }
+}
+
+/// The row of the `entity::Model` conversion to insert, with `id` unset.
+impl TryFrom<&PolicyRule> for entity::ActiveModel {
+    type Error = Error;
+
+    fn try_from(rule: &PolicyRule) -> Result<Self, Self::Error> {
+        use sea_orm::ActiveModelTrait;
+
+        let model = entity::Model::try_from(rule)?;
+
+        Ok(entity::ActiveModel {
+            id: NotSet,
+            ..entity::ActiveModel::from(model).reset_all()
+        })
+    }
+}
+
+impl TryFrom<PolicyRule> for entity::ActiveModel {
+    type Error = Error;
+
+    fn try_from(rule: PolicyRule) -> Result<Self, Self::Error> {
+        entity::ActiveModel::try_from(&rule)
+    }
+}
+
+/// A `[ptype, v0, v1, ...]` vector, like a parsed CSV line.
+impl TryFrom<Vec<String>> for PolicyRule {
+    type Error = Error;
+
+    fn try_from(mut line: Vec<String>) -> Result<Self, Self::Error> {
+        let ptype = if line.is_empty() {
+            String::new()
+        } else {
+            line.remove(0)
+        };
+        let rule = PolicyRule::new(ptype, line);
+
+        rule.check()
+            .map_err(|reason| Error::InvalidRule { line: 0, reason })?;
+
+        Ok(rule)
+    }
+}
+
+/// A `[ptype, v0, v1, ...]` vector, use `values` for the vector casbin passes to adapters.
+impl From<PolicyRule> for Vec<String> {
+    fn from(rule: PolicyRule) -> Self {
+        let mut line = Vec::with_capacity(rule.values.len() + 1);
+        line.push(rule.ptype);
+        line.extend(rule.values);
+        line
+    }
+}
+
+/// A `ptype, v0, v1, ...` line with casbin's CSV quoting.
+///
+/// Values that casbin can't read back, such as values with line breaks, are written as they are,
+/// `export_csv` rejects them instead.
+impl fmt::Display for PolicyRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&csv::quote_field(&self.ptype))?;
+
+        for value in &self.values {
+            write!(f, ", {}", csv::quote_field(value))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for PolicyRule {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match csv::parse_rule(s, 0)? {
+            Some(rule) => Ok(rule),
+            None => Err(Error::InvalidRule {
+                line: 0,
+                reason: "a blank line or a comment is not a rule".to_owned(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_owned(v: &[&str]) -> Vec<String> {
+        v.iter().map(|x| x.to_string()).collect()
+    }
+
+    #[test]
+    fn test_model_conversion() {
+        use sea_orm::ActiveValue::Set;
+
+        let rule = PolicyRule::new("p", to_owned(&["alice", "", "read"]));
+
+        let active_model = entity::ActiveModel::try_from(&rule).unwrap();
+        assert_eq!(active_model.id, NotSet);
+        assert_eq!(active_model.v2, Set(Some("read".to_owned())));
+        assert_eq!(active_model.v5, Set(Some(String::new())));
+        assert_eq!(active_model.arity, Set(Some(3)));
+        assert_eq!(PolicyRule::try_from(active_model).unwrap(), rule);
+
+        // the hash the adapter computes with `SchemaOptions::hashed_values`
+        let model = entity::Model::try_from(&rule).unwrap();
+        let mut rule_with_type = RuleWithType::from_rule("p", Rule::from_string(&rule.values));
+        rule_with_type.hashed = true;
+        assert_eq!(model.id, 0);
+        assert_eq!(model.rule_hash, Some(rule_with_type.hash()));
+        assert_eq!(PolicyRule::from(model), rule);
+
+        // trailing empty values are recorded by the arity
+        let rule = PolicyRule::new("p", to_owned(&["alice", ""]));
+        assert_eq!(
+            PolicyRule::from(entity::Model::try_from(&rule).unwrap()),
+            rule
+        );
+
+        let model = entity::Model {
+            id: 1,
+            ptype: "g".to_owned(),
+            v0: Some("alice".to_owned()),
+            v1: Some("admin".to_owned()),
+            v2: Some(String::new()),
+            v3: None,
+            v4: None,
+            v5: None,
+            expires_at: None,
+            deleted_at: None,
+            arity: None,
+            rule_hash: None,
+        };
+        assert_eq!(
+            PolicyRule::from(model),
+            PolicyRule::new("g", to_owned(&["alice", "admin"]))
+        );
+
+        assert!(entity::ActiveModel::try_from(PolicyRule::new("p", Vec::new())).is_err());
+        assert!(entity::Model::try_from(PolicyRule::new("", to_owned(&["alice"]))).is_err());
+        assert!(PolicyRule::try_from(entity::ActiveModel::default()).is_err());
+    }
+
+    #[test]
+    fn test_vec_conversion() {
+        let rule = PolicyRule::try_from(to_owned(&["p", "alice", "data1", "read"])).unwrap();
+        assert_eq!(
+            rule,
+            PolicyRule::new("p", to_owned(&["alice", "data1", "read"]))
+        );
+        assert_eq!(Vec::from(rule), to_owned(&["p", "alice", "data1", "read"]));
+
+        assert!(PolicyRule::try_from(to_owned(&["p"])).is_err());
+        assert!(PolicyRule::try_from(Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_csv_form() {
+        let rule = PolicyRule::new("p", to_owned(&["alice", "a, b", "read"]));
+        assert_eq!(rule.to_string(), "p, alice, \"a, b\", read");
+        assert_eq!(rule.to_string().parse::<PolicyRule>().unwrap(), rule);
+
+        assert!("# comment".parse::<PolicyRule>().is_err());
+        assert!("p".parse::<PolicyRule>().is_err());
+    }
+}
@@ -0,0 +1,383 @@
+use std::fmt;
+
+use sea_orm::{
+    sea_query::{ColumnSpec, ColumnType, StringLen},
+    ConnectionTrait, DatabaseBackend, DbErr, Statement,
+};
+
+use crate::{migration, SchemaOptions};
+
+/// Type of a column, as declared by `up` or reported by the database catalog.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ColumnKind {
+    BigInteger,
+    Integer,
+    Varchar(u32),
+    Char(u32),
+    Text,
+    /// A type the adapter never creates, named by the catalog.
+    Other(String),
+}
+
+impl fmt::Display for ColumnKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ColumnKind::BigInteger => f.write_str("bigint"),
+            ColumnKind::Integer => f.write_str("integer"),
+            ColumnKind::Varchar(length) => write!(f, "varchar({length})"),
+            ColumnKind::Char(length) => write!(f, "char({length})"),
+            ColumnKind::Text => f.write_str("text"),
+            ColumnKind::Other(name) => f.write_str(name),
+        }
+    }
+}
+
+/// A difference between the live `casbin_rule` table and the one `up_with_options` would create.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SchemaDifference {
+    /// There is no `casbin_rule` table, the other differences are not reported.
+    MissingTable,
+    MissingColumn {
+        column: String,
+    },
+    /// A column the adapter doesn't know about.
+    UnexpectedColumn {
+        column: String,
+    },
+    /// The type differs, including the length of string columns.
+    ColumnType {
+        column: String,
+        expected: ColumnKind,
+        found: ColumnKind,
+    },
+    Nullability {
+        column: String,
+        expected_nullable: bool,
+    },
+    /// No unique index covers exactly these columns, in this order.
+    MissingUniqueKey {
+        columns: Vec<String>,
+    },
+    /// Only checked on MySQL with `SchemaOptions::mysql_collation`.
+    Collation {
+        column: String,
+        expected: String,
+        found: Option<String>,
+    },
+}
+
+impl fmt::Display for SchemaDifference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaDifference::MissingTable => f.write_str("table `casbin_rule` is missing"),
+            SchemaDifference::MissingColumn { column } => write!(f, "column `{column}` is missing"),
+            SchemaDifference::UnexpectedColumn { column } => {
+                write!(f, "column `{column}` is unexpected")
+            }
+            SchemaDifference::ColumnType {
+                column,
+                expected,
+                found,
+            } => write!(f, "column `{column}` is `{found}` instead of `{expected}`"),
+            SchemaDifference::Nullability {
+                column,
+                expected_nullable: true,
+            } => write!(f, "column `{column}` is not nullable"),
+            SchemaDifference::Nullability {
+                column,
+                expected_nullable: false,
+            } => write!(f, "column `{column}` is nullable"),
+            SchemaDifference::MissingUniqueKey { columns } => {
+                write!(f, "no unique index on ({})", columns.join(", "))
+            }
+            SchemaDifference::Collation {
+                column,
+                expected,
+                found: Some(found),
+            } => write!(
+                f,
+                "column `{column}` uses collation `{found}` instead of `{expected}`"
+            ),
+            SchemaDifference::Collation {
+                column,
+                expected,
+                found: None,
+            } => write!(
+                f,
+                "column `{column}` has no collation, expected `{expected}`"
+            ),
+        }
+    }
+}
+
+struct LiveColumn {
+    name: String,
+    kind: ColumnKind,
+    nullable: bool,
+    collation: Option<String>,
+}
+
+/// Compares the live `casbin_rule` table with the one `up_with_options` would create with `options`,
+/// using the catalog of each backend.
+///
+/// `up` creates missing tables and columns, but never alters existing ones, so an empty result
+/// means the adapter can rely on the table.
+pub async fn verify_schema<C: ConnectionTrait>(
+    conn: &C,
+    options: &SchemaOptions,
+) -> Result<Vec<SchemaDifference>, DbErr> {
+    let backend = conn.get_database_backend();
+    let expected = migration::create_rule_table(backend, options)?;
+    let collation = migration::mysql_collation(backend, options)?;
+
+    let live = live_columns(conn).await?;
+    if live.is_empty() {
+        return Ok(vec![SchemaDifference::MissingTable]);
+    }
+
+    let mut differences = Vec::new();
+
+    for column in expected.get_columns() {
+        let name = column.get_column_name();
+        let spec = column.get_column_spec();
+
+        let Some(found) = live.iter().find(|x| x.name == name) else {
+            differences.push(SchemaDifference::MissingColumn { column: name });
+            continue;
+        };
+
+        let kind = expected_kind(column.get_column_type());
+        if !same_kind(backend, &kind, &found.kind) {
+            differences.push(SchemaDifference::ColumnType {
+                column: name.clone(),
+                expected: kind,
+                found: found.kind.clone(),
+            });
+        }
+
+        let nullable = !spec
+            .iter()
+            .any(|x| matches!(x, ColumnSpec::NotNull | ColumnSpec::PrimaryKey));
+        if nullable != found.nullable {
+            differences.push(SchemaDifference::Nullability {
+                column: name.clone(),
+                expected_nullable: nullable,
+            });
+        }
+
+        let collated = spec
+            .iter()
+            .any(|x| matches!(x, ColumnSpec::Extra(x) if x.starts_with("COLLATE")));
+        if let Some(collation) = collation.filter(|_| collated) {
+            if !found
+                .collation
+                .as_deref()
+                .is_some_and(|x| x.eq_ignore_ascii_case(collation))
+            {
+                differences.push(SchemaDifference::Collation {
+                    column: name,
+                    expected: collation.to_owned(),
+                    found: found.collation.clone(),
+                });
+            }
+        }
+    }
+
+    for column in &live {
+        if !expected
+            .get_columns()
+            .iter()
+            .any(|x| x.get_column_name() == column.name)
+        {
+            differences.push(SchemaDifference::UnexpectedColumn {
+                column: column.name.clone(),
+            });
+        }
+    }
+
+    // SQLite names unique constraints `sqlite_autoindex_*`, so keys are matched by their columns
+    let unique_keys = unique_keys(conn).await?;
+
+    for index in expected.get_indexes() {
+        let columns = index.get_index_spec().get_column_names();
+
+        if index.is_unique_key() && !unique_keys.contains(&columns) {
+            differences.push(SchemaDifference::MissingUniqueKey { columns });
+        }
+    }
+
+    Ok(differences)
+}
+
+fn expected_kind(column_type: Option<&ColumnType>) -> ColumnKind {
+    match column_type {
+        Some(ColumnType::BigInteger) => ColumnKind::BigInteger,
+        Some(ColumnType::Integer) => ColumnKind::Integer,
+        Some(ColumnType::String(StringLen::N(length))) => ColumnKind::Varchar(*length),
+        Some(ColumnType::Char(Some(length))) => ColumnKind::Char(*length),
+        Some(ColumnType::Text) => ColumnKind::Text,
+        other => ColumnKind::Other(format!("{other:?}")),
+    }
+}
+
+// SQLite stores every integer as 64 bits and creates an auto-increment `bigint` key as `integer`
+fn same_kind(backend: DatabaseBackend, expected: &ColumnKind, found: &ColumnKind) -> bool {
+    let is_integer = |x: &ColumnKind| matches!(x, ColumnKind::BigInteger | ColumnKind::Integer);
+
+    expected == found
+        || (backend == DatabaseBackend::Sqlite && is_integer(expected) && is_integer(found))
+}
+
+// `data_type` is `varchar` on MySQL, `character varying` on Postgres, and `varchar(125)` on SQLite,
+// where the length is part of the declared type
+fn live_kind(data_type: &str, length: Option<i64>) -> ColumnKind {
+    let data_type = data_type.trim().to_lowercase();
+
+    let (name, length) = match data_type.split_once('(') {
+        Some((name, rest)) => (
+            name.trim(),
+            rest.trim_end_matches(')').trim().parse::<u32>().ok(),
+        ),
+        None => (
+            data_type.as_str(),
+            length.and_then(|x| u32::try_from(x).ok()),
+        ),
+    };
+
+    match (name, length) {
+        ("bigint" | "int8", _) => ColumnKind::BigInteger,
+        ("int" | "integer" | "int4", _) => ColumnKind::Integer,
+        ("varchar" | "character varying", Some(length)) => ColumnKind::Varchar(length),
+        ("char" | "character" | "bpchar", Some(length)) => ColumnKind::Char(length),
+        ("text", _) => ColumnKind::Text,
+        _ => ColumnKind::Other(data_type.clone()),
+    }
+}
+
+async fn live_columns<C: ConnectionTrait>(conn: &C) -> Result<Vec<LiveColumn>, DbErr> {
+    let backend = conn.get_database_backend();
+
+    // MySQL 8 and Postgres report catalog columns with their own types, hence the casts
+    let sql = match backend {
+        DatabaseBackend::MySql => {
+            "SELECT CAST(column_name AS CHAR) AS name, CAST(data_type AS CHAR) AS data_type, \
+             CAST(character_maximum_length AS SIGNED) AS length, \
+             CAST(is_nullable AS CHAR) AS nullable, CAST(collation_name AS CHAR) AS collation \
+             FROM information_schema.columns \
+             WHERE table_schema = DATABASE() AND table_name = ? \
+             ORDER BY ordinal_position"
+        }
+        DatabaseBackend::Postgres => {
+            "SELECT column_name::text AS name, data_type::text AS data_type, \
+             character_maximum_length::bigint AS length, is_nullable::text AS nullable, \
+             collation_name::text AS collation \
+             FROM information_schema.columns \
+             WHERE table_schema = current_schema() AND table_name = $1 \
+             ORDER BY ordinal_position"
+        }
+        DatabaseBackend::Sqlite => {
+            "SELECT name, type AS data_type, NULL AS length, \
+             CASE WHEN \"notnull\" = 0 AND pk = 0 THEN 'YES' ELSE 'NO' END AS nullable, \
+             NULL AS collation \
+             FROM pragma_table_info(?) ORDER BY cid"
+        }
+    };
+
+    let stmt = Statement::from_sql_and_values(backend, sql, ["casbin_rule".into()]);
+    let mut columns = Vec::new();
+
+    for row in conn.query_all(stmt).await? {
+        let data_type = row.try_get::<String>("", "data_type")?;
+        let length = row.try_get::<Option<i64>>("", "length")?;
+
+        columns.push(LiveColumn {
+            name: row.try_get("", "name")?,
+            kind: live_kind(&data_type, length),
+            nullable: row.try_get::<String>("", "nullable")? == "YES",
+            collation: row.try_get("", "collation")?,
+        });
+    }
+
+    Ok(columns)
+}
+
+// the columns of each unique index, in index order
+async fn unique_keys<C: ConnectionTrait>(conn: &C) -> Result<Vec<Vec<String>>, DbErr> {
+    let backend = conn.get_database_backend();
+
+    let sql = match backend {
+        DatabaseBackend::MySql => {
+            "SELECT CAST(index_name AS CHAR) AS name, CAST(column_name AS CHAR) AS column_name \
+             FROM information_schema.statistics \
+             WHERE table_schema = DATABASE() AND table_name = ? AND non_unique = 0 \
+             ORDER BY index_name, seq_in_index"
+        }
+        DatabaseBackend::Postgres => {
+            "SELECT i.relname::text AS name, a.attname::text AS column_name \
+             FROM pg_index ix \
+             JOIN pg_class t ON t.oid = ix.indrelid \
+             JOIN pg_class i ON i.oid = ix.indexrelid \
+             JOIN pg_namespace n ON n.oid = t.relnamespace \
+             JOIN LATERAL unnest(ix.indkey) WITH ORDINALITY AS k(attnum, position) ON true \
+             JOIN pg_attribute a ON a.attrelid = t.oid AND a.attnum = k.attnum \
+             WHERE n.nspname = current_schema() AND t.relname = $1 AND ix.indisunique \
+             ORDER BY i.relname, k.position"
+        }
+        DatabaseBackend::Sqlite => {
+            "SELECT il.name AS name, ii.name AS column_name \
+             FROM pragma_index_list(?) AS il, pragma_index_info(il.name) AS ii \
+             WHERE il.\"unique\" = 1 \
+             ORDER BY il.name, ii.seqno"
+        }
+    };
+
+    let stmt = Statement::from_sql_and_values(backend, sql, ["casbin_rule".into()]);
+    let mut keys: Vec<(String, Vec<String>)> = Vec::new();
+
+    for row in conn.query_all(stmt).await? {
+        let name = row.try_get::<String>("", "name")?;
+        let column = row.try_get::<String>("", "column_name")?;
+
+        match keys.last_mut() {
+            Some((last, columns)) if *last == name => columns.push(column),
+            _ => keys.push((name, vec![column])),
+        }
+    }
+
+    Ok(keys.into_iter().map(|(_, columns)| columns).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_live_kind() {
+        assert_eq!(live_kind("varchar(125)", None), ColumnKind::Varchar(125));
+        assert_eq!(
+            live_kind("character varying", Some(18)),
+            ColumnKind::Varchar(18)
+        );
+        assert_eq!(live_kind("char", Some(64)), ColumnKind::Char(64));
+        assert_eq!(live_kind("INTEGER", None), ColumnKind::Integer);
+        assert_eq!(live_kind("bigint", None), ColumnKind::BigInteger);
+        assert_eq!(live_kind("text", None), ColumnKind::Text);
+        assert_eq!(
+            live_kind("datetime", None),
+            ColumnKind::Other("datetime".to_owned())
+        );
+
+        assert!(same_kind(
+            DatabaseBackend::Sqlite,
+            &ColumnKind::BigInteger,
+            &ColumnKind::Integer
+        ));
+        assert!(!same_kind(
+            DatabaseBackend::Postgres,
+            &ColumnKind::BigInteger,
+            &ColumnKind::Integer
+        ));
+    }
+}
@@ -0,0 +1,175 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use casbin::{error::AdapterError, Error as CasbinError, Result};
+use sea_orm::{
+    ActiveValue::{NotSet, Set},
+    ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, QueryOrder,
+};
+
+use crate::{
+    action,
+    entity::{snapshot, snapshot_rule},
+    telemetry, Error,
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Snapshot {
+    pub id: i64,
+    pub name: String,
+    pub created_at: SystemTime,
+}
+
+impl From<snapshot::Model> for Snapshot {
+    fn from(value: snapshot::Model) -> Self {
+        Snapshot {
+            id: value.id,
+            name: value.name,
+            created_at: UNIX_EPOCH + Duration::from_secs(value.created_at.max(0) as u64),
+        }
+    }
+}
+
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(backend = ?conn.get_database_backend(), name = name, rows_affected = tracing::field::Empty)
+    )
+)]
+pub(crate) async fn create_snapshot<C: ConnectionTrait>(conn: &C, name: &str) -> Result<Snapshot> {
+    let rules = action::load_undeleted_policy(conn).await?;
+
+    let model = snapshot::ActiveModel {
+        id: NotSet,
+        name: Set(name.to_owned()),
+        created_at: Set(action::unix_timestamp(SystemTime::now())),
+    };
+
+    let snapshot = snapshot::Entity::insert(model)
+        .exec_with_returning(conn)
+        .await
+        .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))?;
+
+    for chunk in rules.chunks(action::BATCH_SIZE) {
+        let models = chunk.iter().map(|rule| snapshot_rule::ActiveModel {
+            id: NotSet,
+            snapshot_id: Set(snapshot.id),
+            ptype: Set(rule.ptype.clone()),
+            v0: Set(rule.v0.clone()),
+            v1: Set(rule.v1.clone()),
+            v2: Set(rule.v2.clone()),
+            v3: Set(rule.v3.clone()),
+            v4: Set(rule.v4.clone()),
+            v5: Set(rule.v5.clone()),
+            expires_at: Set(rule.expires_at),
+            arity: Set(rule.arity),
+        });
+
+        snapshot_rule::Entity::insert_many(models)
+            .exec_without_returning(conn)
+            .await
+            .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))?;
+    }
+
+    telemetry::record("rows_affected", rules.len() as u64 + 1);
+
+    Ok(snapshot.into())
+}
+
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(backend = ?conn.get_database_backend(), snapshots = tracing::field::Empty)
+    )
+)]
+pub(crate) async fn list_snapshots<C: ConnectionTrait>(conn: &C) -> Result<Vec<Snapshot>> {
+    let snapshots = snapshot::Entity::find()
+        .order_by_asc(snapshot::Column::Id)
+        .all(conn)
+        .await
+        .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))?;
+    telemetry::record("snapshots", snapshots.len() as u64);
+
+    Ok(snapshots.into_iter().map(Snapshot::from).collect())
+}
+
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(backend = ?conn.get_database_backend(), name = name)
+    )
+)]
+pub(crate) async fn find_snapshot<C: ConnectionTrait>(conn: &C, name: &str) -> Result<Snapshot> {
+    snapshot::Entity::find()
+        .filter(snapshot::Column::Name.eq(name))
+        .one(conn)
+        .await
+        .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))?
+        .map(Snapshot::from)
+        .ok_or_else(|| {
+            CasbinError::from(AdapterError(Box::new(Error::SnapshotNotFound(
+                name.to_owned(),
+            ))))
+        })
+}
+
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(backend = ?conn.get_database_backend(), snapshot_id = snapshot_id, rules = tracing::field::Empty)
+    )
+)]
+pub(crate) async fn snapshot_rules<C: ConnectionTrait>(
+    conn: &C,
+    snapshot_id: i64,
+) -> Result<Vec<snapshot_rule::Model>> {
+    let rules = snapshot_rule::Entity::find()
+        .filter(snapshot_rule::Column::SnapshotId.eq(snapshot_id))
+        .order_by_asc(snapshot_rule::Column::Id)
+        .all(conn)
+        .await
+        .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))?;
+    telemetry::record("rules", rules.len() as u64);
+
+    Ok(rules)
+}
+
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(backend = ?conn.get_database_backend(), name = name, rows_affected = tracing::field::Empty)
+    )
+)]
+pub(crate) async fn delete_snapshot<C: ConnectionTrait>(conn: &C, name: &str) -> Result<bool> {
+    let Some(snapshot) = snapshot::Entity::find()
+        .filter(snapshot::Column::Name.eq(name))
+        .one(conn)
+        .await
+        .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))?
+    else {
+        return Ok(false);
+    };
+
+    let rules = snapshot_rule::Entity::delete_many()
+        .filter(snapshot_rule::Column::SnapshotId.eq(snapshot.id))
+        .exec(conn)
+        .await
+        .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))?;
+
+    let deleted = snapshot::Entity::delete_by_id(snapshot.id)
+        .exec(conn)
+        .await
+        .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))?;
+    telemetry::record("rows_affected", rules.rows_affected + deleted.rows_affected);
+
+    Ok(deleted.rows_affected == 1)
+}
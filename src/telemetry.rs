@@ -0,0 +1,255 @@
+//! Spans of the `tracing` feature and metrics of the `metrics` feature, the helpers do nothing
+//! without them.
+
+use std::future::Future;
+#[cfg(any(feature = "tracing", feature = "metrics"))]
+use std::time::Instant;
+#[cfg(feature = "metrics")]
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, PoisonError,
+    },
+};
+
+use casbin::{error::AdapterError, Error as CasbinError, Result};
+use sea_orm::{DatabaseBackend, DatabaseTransaction, TransactionTrait};
+#[cfg(feature = "tracing")]
+use sea_orm::{DbErr, SqlErr};
+
+use crate::AdapterOptions;
+
+/// An operation of `SeaOrmAdapter`, its metrics are labelled by `operation` and `backend`.
+pub(crate) struct Operation {
+    #[cfg(feature = "metrics")]
+    labels: [(&'static str, &'static str); 2],
+    // a transaction was begun and not committed yet
+    #[cfg(feature = "metrics")]
+    open: AtomicBool,
+    #[cfg(feature = "tracing")]
+    redact_values: bool,
+}
+
+#[cfg_attr(
+    not(all(feature = "tracing", feature = "metrics")),
+    allow(unused_variables)
+)]
+impl Operation {
+    pub(crate) fn new(
+        operation: &'static str,
+        backend: DatabaseBackend,
+        options: &AdapterOptions,
+    ) -> Self {
+        Operation {
+            #[cfg(feature = "metrics")]
+            labels: [("operation", operation), ("backend", backend_name(backend))],
+            #[cfg(feature = "metrics")]
+            open: AtomicBool::new(false),
+            #[cfg(feature = "tracing")]
+            redact_values: options.redact_values,
+        }
+    }
+
+    /// Begins the transaction of the operation, a failure until it's committed counts as a
+    /// rollback.
+    pub(crate) async fn begin<C: TransactionTrait>(&self, conn: &C) -> Result<DatabaseTransaction> {
+        let txn = conn
+            .begin()
+            .await
+            .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))?;
+
+        #[cfg(feature = "metrics")]
+        self.open.store(true, Ordering::Relaxed);
+
+        Ok(txn)
+    }
+
+    pub(crate) async fn commit(&self, txn: DatabaseTransaction) -> Result<()> {
+        txn.commit()
+            .await
+            .map_err(|err| CasbinError::from(AdapterError(Box::new(err))))?;
+
+        #[cfg(feature = "metrics")]
+        self.open.store(false, Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    /// Runs the operation, and records its duration in the `duration_ms` field of the current span
+    /// and in the metrics, along with its failure.
+    ///
+    /// The error is recorded as an event of the span, only with its `kind` with
+    /// `AdapterOptions::redact_values`, database messages can quote the values of the rule.
+    pub(crate) async fn observe<T>(&self, future: impl Future<Output = Result<T>>) -> Result<T> {
+        #[cfg(any(feature = "tracing", feature = "metrics"))]
+        let start = Instant::now();
+
+        let result = future.await;
+
+        #[cfg(any(feature = "tracing", feature = "metrics"))]
+        let elapsed = start.elapsed();
+
+        #[cfg(feature = "tracing")]
+        {
+            tracing::Span::current().record("duration_ms", elapsed.as_secs_f64() * 1000.0);
+
+            if let Err(err) = &result {
+                let kind = error_kind(err);
+
+                if self.redact_values {
+                    tracing::error!(kind);
+                } else {
+                    tracing::error!(kind, error = %err);
+                }
+            }
+        }
+
+        #[cfg(feature = "metrics")]
+        {
+            metrics::histogram!("sea_orm_adapter_duration_seconds", &self.labels).record(elapsed);
+
+            if let Err(err) = &result {
+                metrics::counter!("sea_orm_adapter_errors_total", &self.labels).increment(1);
+
+                if is_conflict(err) {
+                    metrics::counter!("sea_orm_adapter_conflicts_total", &self.labels).increment(1);
+                }
+
+                if self.open.load(Ordering::Relaxed) {
+                    metrics::counter!("sea_orm_adapter_rollbacks_total", &self.labels).increment(1);
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Rows read from `casbin_rule`.
+    pub(crate) fn loaded(&self, rows: u64) {
+        #[cfg(feature = "metrics")]
+        metrics::counter!("sea_orm_adapter_rows_loaded_total", &self.labels).increment(rows);
+    }
+
+    /// Rows read but not loaded into the model.
+    pub(crate) fn skipped(&self, rows: u64) {
+        #[cfg(feature = "metrics")]
+        metrics::counter!("sea_orm_adapter_rows_skipped_total", &self.labels).increment(rows);
+    }
+
+    /// Rules added, including revived ones.
+    pub(crate) fn inserted(&self, rows: u64) {
+        #[cfg(feature = "metrics")]
+        metrics::counter!("sea_orm_adapter_rows_inserted_total", &self.labels).increment(rows);
+    }
+
+    /// Rules removed, including soft deleted ones.
+    pub(crate) fn deleted(&self, rows: u64) {
+        #[cfg(feature = "metrics")]
+        metrics::counter!("sea_orm_adapter_rows_deleted_total", &self.labels).increment(rows);
+    }
+
+    /// Sets the number of rules of each ptype, after a load of every rule, and resets the ptypes
+    /// of the previous loads that have no rules anymore.
+    pub(crate) fn count_rules<'a>(&self, ptypes: impl Iterator<Item = &'a str>) {
+        #[cfg(feature = "metrics")]
+        {
+            // the gauges are shared by the adapters of a backend, like their labels
+            static REPORTED: Mutex<Vec<(&str, String)>> = Mutex::new(Vec::new());
+
+            let backend = self.labels[1].1;
+            let mut counts = HashMap::<_, u64>::new();
+            for ptype in ptypes {
+                *counts.entry(ptype).or_default() += 1;
+            }
+
+            let mut reported = REPORTED.lock().unwrap_or_else(PoisonError::into_inner);
+
+            for (_, ptype) in reported.iter().filter(|(x, _)| *x == backend) {
+                if !counts.contains_key(ptype.as_str()) {
+                    let labels = [("backend", backend.to_owned()), ("ptype", ptype.clone())];
+                    metrics::gauge!("sea_orm_adapter_rules", &labels).set(0.0);
+                }
+            }
+
+            for (ptype, count) in counts {
+                if !reported.iter().any(|(x, y)| *x == backend && y == ptype) {
+                    reported.push((backend, ptype.to_owned()));
+                }
+
+                let labels = [("backend", backend.to_owned()), ("ptype", ptype.to_owned())];
+                metrics::gauge!("sea_orm_adapter_rules", &labels).set(count as f64);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "tracing")]
+fn error_kind(err: &casbin::Error) -> &'static str {
+    use crate::Error;
+
+    let casbin::Error::AdapterError(err) = err else {
+        return "casbin";
+    };
+
+    if let Some(err) = err.0.downcast_ref::<Error>() {
+        return match err {
+            Error::SnapshotNotFound(_) => "snapshot_not_found",
+            Error::ModelNotFound { .. } => "model_not_found",
+            Error::InvalidRule { .. } => "invalid_rule",
+            Error::RulesSkipped(_) => "rules_skipped",
+            Error::InvalidCursor(_) => "invalid_cursor",
+            Error::CollationMismatch { .. } => "collation_mismatch",
+            Error::SchemaMismatch(_) => "schema_mismatch",
+            Error::MigrationLockTimeout(_) => "migration_lock_timeout",
+            Error::Conflict { .. } => "conflict",
+        };
+    }
+
+    if err.0.is::<std::io::Error>() {
+        return "io";
+    }
+
+    match err.0.downcast_ref::<DbErr>().map(DbErr::sql_err) {
+        Some(Some(SqlErr::UniqueConstraintViolation(_))) => "unique_constraint_violation",
+        Some(Some(SqlErr::ForeignKeyConstraintViolation(_))) => "foreign_key_constraint_violation",
+        Some(_) => "database",
+        None => "adapter",
+    }
+}
+
+#[cfg(feature = "metrics")]
+fn backend_name(backend: DatabaseBackend) -> &'static str {
+    match backend {
+        DatabaseBackend::MySql => "mysql",
+        DatabaseBackend::Postgres => "postgres",
+        DatabaseBackend::Sqlite => "sqlite",
+    }
+}
+
+// a concurrent save, not a rule that is already stored
+#[cfg(feature = "metrics")]
+fn is_conflict(err: &casbin::Error) -> bool {
+    let casbin::Error::AdapterError(err) = err else {
+        return false;
+    };
+
+    matches!(err.0.downcast_ref(), Some(crate::Error::Conflict { .. }))
+}
+
+/// Records a count, such as `rules` or `rows_affected`, in the current span.
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+pub(crate) fn record(field: &'static str, value: u64) {
+    #[cfg(feature = "tracing")]
+    tracing::Span::current().record(field, value);
+}
+
+/// Records the values of a rule in the `rule` field of the current span, unless
+/// `AdapterOptions::redact_values` is set.
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+pub(crate) fn record_rule(options: &AdapterOptions, rule: &[String]) {
+    #[cfg(feature = "tracing")]
+    if !options.redact_values {
+        tracing::Span::current().record("rule", tracing::field::debug(rule));
+    }
+}